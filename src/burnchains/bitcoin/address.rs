@@ -0,0 +1,428 @@
+/*
+ copyright: (c) 2013-2018 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use burnchains::bitcoin::BitcoinNetworkType;
+
+// opcodes relevant to recognizing the handful of scriptPubKey shapes below
+const OP_0: u8 = 0x00;
+const OP_DUP: u8 = 0x76;
+const OP_HASH160: u8 = 0xa9;
+const OP_EQUAL: u8 = 0x87;
+const OP_EQUALVERIFY: u8 = 0x88;
+const OP_CHECKSIG: u8 = 0xac;
+
+/// The smallest witness-version opcode, OP_0. Witness versions 1-16 are pushed as OP_1..OP_16
+/// (0x51..0x60), which are numbered contiguously from `OP_1`.
+const OP_1: u8 = 0x51;
+const OP_16: u8 = 0x60;
+
+/// The handful of scriptPubKey shapes Blockstack cares about when resolving a Bitcoin output
+/// (or a signer's input address) to an address that can be carried in a burn op. Legacy
+/// (pre-segwit) P2PKH/P2SH addresses commit to a 20-byte hash directly in the scriptPubKey;
+/// native segwit (BIP141/BIP173, and BIP350 for v1+) addresses commit to a witness program
+/// instead, tagged by witness version 0-16 (P2WPKH/P2WSH are version 0, Taproot is version 1;
+/// versions 2-16 are reserved for future upgrades but already well-formed today).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BitcoinAddress {
+    /// pay-to-pubkey-hash: OP_DUP OP_HASH160 <20-byte hash> OP_EQUALVERIFY OP_CHECKSIG
+    PublicKeyHash(BitcoinNetworkType, [u8; 20]),
+    /// pay-to-script-hash: OP_HASH160 <20-byte hash> OP_EQUAL
+    ScriptHash(BitcoinNetworkType, [u8; 20]),
+    /// native segwit, any witness version: <OP_0 | OP_1..OP_16> <2-to-40-byte witness program>
+    SegWit { network: BitcoinNetworkType, version: u8, program: Vec<u8> },
+}
+
+impl BitcoinAddress {
+    /// Recognizes a legacy or native-segwit scriptPubKey (any witness version) and extracts the
+    /// address bytes it commits to. Returns `None` for any other (e.g. bare multisig or
+    /// nonstandard) script, since those can't be attributed to a single signer.
+    pub fn from_scriptpubkey(network: BitcoinNetworkType, script: &[u8]) -> Option<BitcoinAddress> {
+        // legacy P2PKH: 76 a9 14 <20> 88 ac
+        if script.len() == 25
+            && script[0] == OP_DUP && script[1] == OP_HASH160 && script[2] == 0x14
+            && script[23] == OP_EQUALVERIFY && script[24] == OP_CHECKSIG {
+            let mut hash = [0u8; 20];
+            hash.copy_from_slice(&script[3..23]);
+            return Some(BitcoinAddress::PublicKeyHash(network, hash));
+        }
+
+        // legacy P2SH: a9 14 <20> 87
+        if script.len() == 23
+            && script[0] == OP_HASH160 && script[1] == 0x14
+            && script[22] == OP_EQUAL {
+            let mut hash = [0u8; 20];
+            hash.copy_from_slice(&script[2..22]);
+            return Some(BitcoinAddress::ScriptHash(network, hash));
+        }
+
+        // native segwit, any version: <version opcode> <program-length> <program>
+        if script.len() >= 2 {
+            let version = match script[0] {
+                OP_0 => Some(0u8),
+                op if op >= OP_1 && op <= OP_16 => Some(op - OP_1 + 1),
+                _ => None,
+            };
+
+            if let Some(version) = version {
+                let program_len = script[1] as usize;
+                if program_len >= 2 && program_len <= 40 && script.len() == 2 + program_len {
+                    return Some(BitcoinAddress::SegWit { network, version, program: script[2..].to_vec() });
+                }
+            }
+        }
+
+        None
+    }
+
+    /// The address's underlying hash/witness-program bytes -- the bytes that downstream
+    /// Blockstack operations check signer/recipient identity against.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            BitcoinAddress::PublicKeyHash(_, hash) => hash.to_vec(),
+            BitcoinAddress::ScriptHash(_, hash) => hash.to_vec(),
+            BitcoinAddress::SegWit { program, .. } => program.clone(),
+        }
+    }
+
+    /// The conventional Blockstack "burn" address: a P2PKH output to the all-zeros hash160,
+    /// which nobody holds the preimage to.
+    pub fn is_burn(&self) -> bool {
+        match self {
+            BitcoinAddress::PublicKeyHash(_, hash) => hash.iter().all(|b| *b == 0),
+            _ => false,
+        }
+    }
+
+    /// This address's bech32 (witness v0) or bech32m (BIP350, witness v1+) text encoding.
+    /// Legacy `PublicKeyHash`/`ScriptHash` addresses have no bech32 form -- they're base58check
+    /// addresses, which this module doesn't encode -- and so return `None`.
+    pub fn to_bech32(&self) -> Option<String> {
+        match self {
+            BitcoinAddress::SegWit { network, version, program } =>
+                Some(bech32::encode(bech32::hrp_for_network(*network), *version, program)),
+            _ => None,
+        }
+    }
+
+    /// Parses a bech32/bech32m address string back into a `SegWit` address, validating its
+    /// checksum, human-readable part, and witness-version/program-length rules per BIP173/350.
+    pub fn from_bech32(addr: &str) -> Option<BitcoinAddress> {
+        let (hrp, version, program) = bech32::decode(addr)?;
+        let network = bech32::network_for_hrp(&hrp)?;
+        Some(BitcoinAddress::SegWit { network, version, program })
+    }
+}
+
+/// A minimal bech32 (BIP173) / bech32m (BIP350) text codec for native segwit addresses. Encodes
+/// a witness version and program under a network's human-readable part (`bc` mainnet, `tb`
+/// testnet, `bcrt` regtest); witness version 0 uses the original bech32 checksum constant,
+/// version 1 and up (BIP350, e.g. Taproot) uses the bech32m constant, per BIP350's fix for
+/// bech32's weakness against certain single-character substitutions in longer strings.
+mod bech32 {
+    use burnchains::bitcoin::BitcoinNetworkType;
+
+    const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+    const BECH32_CONST: u32 = 1;
+    const BECH32M_CONST: u32 = 0x2bc830a3;
+
+    pub fn hrp_for_network(network: BitcoinNetworkType) -> &'static str {
+        match network {
+            BitcoinNetworkType::Mainnet => "bc",
+            BitcoinNetworkType::Testnet => "tb",
+            BitcoinNetworkType::Regtest => "bcrt",
+        }
+    }
+
+    pub fn network_for_hrp(hrp: &str) -> Option<BitcoinNetworkType> {
+        match hrp {
+            "bc" => Some(BitcoinNetworkType::Mainnet),
+            "tb" => Some(BitcoinNetworkType::Testnet),
+            "bcrt" => Some(BitcoinNetworkType::Regtest),
+            _ => None,
+        }
+    }
+
+    fn polymod(values: &[u8]) -> u32 {
+        let generators = [0x3b6a57b2u32, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+        let mut chk: u32 = 1;
+        for &value in values {
+            let top = chk >> 25;
+            chk = (chk & 0x1ffffff) << 5 ^ (value as u32);
+            for i in 0..5 {
+                if (top >> i) & 1 == 1 {
+                    chk ^= generators[i];
+                }
+            }
+        }
+        chk
+    }
+
+    fn hrp_expand(hrp: &str) -> Vec<u8> {
+        let mut out = Vec::with_capacity(hrp.len() * 2 + 1);
+        out.extend(hrp.bytes().map(|b| b >> 5));
+        out.push(0);
+        out.extend(hrp.bytes().map(|b| b & 0x1f));
+        out
+    }
+
+    fn checksum_const(version: u8) -> u32 {
+        if version == 0 { BECH32_CONST } else { BECH32M_CONST }
+    }
+
+    fn create_checksum(hrp: &str, data: &[u8], version: u8) -> [u8; 6] {
+        let mut values = hrp_expand(hrp);
+        values.extend_from_slice(data);
+        values.extend_from_slice(&[0u8; 6]);
+
+        let polymod_value = polymod(&values) ^ checksum_const(version);
+        let mut checksum = [0u8; 6];
+        for i in 0..6 {
+            checksum[i] = ((polymod_value >> (5 * (5 - i))) & 0x1f) as u8;
+        }
+        checksum
+    }
+
+    fn verify_checksum(hrp: &str, data: &[u8], version: u8) -> bool {
+        let mut values = hrp_expand(hrp);
+        values.extend_from_slice(data);
+        polymod(&values) == checksum_const(version)
+    }
+
+    /// Regroups `data` (a sequence of `from_bits`-wide values) into `to_bits`-wide values,
+    /// padding the last group with zero bits if `pad` is set -- used both to go from 8-bit
+    /// witness-program bytes to 5-bit bech32 groups, and back.
+    fn convert_bits(data: &[u8], from_bits: u32, to_bits: u32, pad: bool) -> Option<Vec<u8>> {
+        let mut acc: u32 = 0;
+        let mut bits: u32 = 0;
+        let mut out = Vec::new();
+        let max_value = (1u32 << to_bits) - 1;
+
+        for &value in data {
+            if (value as u32) >> from_bits != 0 {
+                return None;
+            }
+            acc = (acc << from_bits) | (value as u32);
+            bits += from_bits;
+            while bits >= to_bits {
+                bits -= to_bits;
+                out.push(((acc >> bits) & max_value) as u8);
+            }
+        }
+
+        if pad {
+            if bits > 0 {
+                out.push(((acc << (to_bits - bits)) & max_value) as u8);
+            }
+        } else if bits >= from_bits || ((acc << (to_bits - bits)) & max_value) != 0 {
+            return None;
+        }
+
+        Some(out)
+    }
+
+    /// Encodes `version`/`program` under `hrp` as a bech32 (version 0) or bech32m (version 1+)
+    /// address string.
+    pub fn encode(hrp: &str, version: u8, program: &[u8]) -> String {
+        let mut data = vec![version];
+        data.extend(convert_bits(program, 8, 5, true).expect("witness programs are always convertible"));
+
+        let checksum = create_checksum(hrp, &data, version);
+
+        let mut out = String::with_capacity(hrp.len() + 1 + data.len() + checksum.len());
+        out.push_str(hrp);
+        out.push('1');
+        for &d in data.iter().chain(checksum.iter()) {
+            out.push(CHARSET[d as usize] as char);
+        }
+        out
+    }
+
+    /// Decodes a bech32/bech32m address string into its human-readable part, witness version,
+    /// and witness program, validating the checksum and BIP173/350 length/version rules.
+    pub fn decode(addr: &str) -> Option<(String, u8, Vec<u8>)> {
+        if addr.len() < 8 || addr.len() > 90 {
+            return None;
+        }
+        if addr != addr.to_lowercase() && addr != addr.to_uppercase() {
+            return None;
+        }
+        let addr = addr.to_lowercase();
+
+        let sep_pos = addr.rfind('1')?;
+        if sep_pos == 0 || sep_pos + 7 > addr.len() {
+            return None;
+        }
+
+        let hrp = &addr[..sep_pos];
+        let data_part = &addr[sep_pos + 1..];
+
+        let mut values = Vec::with_capacity(data_part.len());
+        for c in data_part.chars() {
+            let v = CHARSET.iter().position(|&x| x as char == c)? as u8;
+            values.push(v);
+        }
+
+        let (data, checksum) = values.split_at(values.len() - 6);
+        let version = *data.get(0)?;
+        if version > 16 {
+            return None;
+        }
+
+        let mut combined = data.to_vec();
+        combined.extend_from_slice(checksum);
+        if !verify_checksum(hrp, &combined, version) {
+            return None;
+        }
+
+        let program = convert_bits(&data[1..], 5, 8, false)?;
+        if program.len() < 2 || program.len() > 40 {
+            return None;
+        }
+        if version == 0 && program.len() != 20 && program.len() != 32 {
+            return None;
+        }
+
+        Some((hrp.to_string(), version, program))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::hash::hex_bytes;
+
+    #[test]
+    fn parses_legacy_p2pkh() {
+        let script = hex_bytes("76a9140be3e286a15ea85882761618e366586b5574100d88ac").unwrap();
+        let addr = BitcoinAddress::from_scriptpubkey(BitcoinNetworkType::Testnet, &script).unwrap();
+        assert_eq!(addr.to_bytes(), hex_bytes("0be3e286a15ea85882761618e366586b5574100d").unwrap());
+        assert!(!addr.is_burn());
+    }
+
+    #[test]
+    fn recognizes_the_burn_address() {
+        let script = hex_bytes("76a914000000000000000000000000000000000000000088ac").unwrap();
+        let addr = BitcoinAddress::from_scriptpubkey(BitcoinNetworkType::Testnet, &script).unwrap();
+        assert!(addr.is_burn());
+    }
+
+    #[test]
+    fn parses_legacy_p2sh() {
+        let script = hex_bytes("a9140be3e286a15ea85882761618e366586b5574100d87").unwrap();
+        let addr = BitcoinAddress::from_scriptpubkey(BitcoinNetworkType::Testnet, &script).unwrap();
+        assert_eq!(addr, BitcoinAddress::ScriptHash(BitcoinNetworkType::Testnet,
+            [0x0b,0xe3,0xe2,0x86,0xa1,0x5e,0xa8,0x58,0x82,0x76,0x16,0x18,0xe3,0x66,0x58,0x6b,0x55,0x74,0x10,0x0d]));
+    }
+
+    #[test]
+    fn parses_native_segwit_p2wpkh() {
+        let script = hex_bytes("00140be3e286a15ea85882761618e366586b5574100d").unwrap();
+        let addr = BitcoinAddress::from_scriptpubkey(BitcoinNetworkType::Mainnet, &script).unwrap();
+        assert_eq!(addr.to_bytes(), hex_bytes("0be3e286a15ea85882761618e366586b5574100d").unwrap());
+    }
+
+    #[test]
+    fn parses_native_segwit_p2wsh() {
+        let script = hex_bytes("00203333333333333333333333333333333333333333333333333333333333333333").unwrap();
+        let addr = BitcoinAddress::from_scriptpubkey(BitcoinNetworkType::Mainnet, &script).unwrap();
+        assert_eq!(addr.to_bytes().len(), 32);
+    }
+
+    #[test]
+    fn parses_taproot() {
+        let script = hex_bytes("51203333333333333333333333333333333333333333333333333333333333333333").unwrap();
+        let addr = BitcoinAddress::from_scriptpubkey(BitcoinNetworkType::Mainnet, &script).unwrap();
+        match addr {
+            BitcoinAddress::SegWit { version, program, .. } => {
+                assert_eq!(version, 1);
+                assert_eq!(program.len(), 32);
+            },
+            _ => assert!(false, "expected a SegWit address"),
+        }
+    }
+
+    #[test]
+    fn parses_future_witness_versions() {
+        // witness version 2, a 20-byte program -- reserved today, but already well-formed.
+        let script = hex_bytes("52140be3e286a15ea85882761618e366586b5574100d").unwrap();
+        let addr = BitcoinAddress::from_scriptpubkey(BitcoinNetworkType::Mainnet, &script).unwrap();
+        match addr {
+            BitcoinAddress::SegWit { version, program, .. } => {
+                assert_eq!(version, 2);
+                assert_eq!(program.len(), 20);
+            },
+            _ => assert!(false, "expected a SegWit address"),
+        }
+    }
+
+    #[test]
+    fn rejects_nonstandard_scripts() {
+        let script = hex_bytes("6a0548656c6c6f").unwrap(); // OP_RETURN "Hello"
+        assert_eq!(BitcoinAddress::from_scriptpubkey(BitcoinNetworkType::Mainnet, &script), None);
+    }
+
+    #[test]
+    fn bech32_round_trips_a_p2wpkh_address_per_network() {
+        for network in [BitcoinNetworkType::Mainnet, BitcoinNetworkType::Testnet, BitcoinNetworkType::Regtest].iter() {
+            let script = hex_bytes("00140be3e286a15ea85882761618e366586b5574100d").unwrap();
+            let addr = BitcoinAddress::from_scriptpubkey(*network, &script).unwrap();
+
+            let encoded = addr.to_bech32().unwrap();
+            let decoded = BitcoinAddress::from_bech32(&encoded).unwrap();
+            assert_eq!(decoded, addr);
+        }
+    }
+
+    #[test]
+    fn bech32_round_trips_every_witness_version() {
+        for version in 0u8..=16 {
+            let program: Vec<u8> = if version == 0 {
+                vec![0x11; 20]
+            } else {
+                vec![0x22; 32]
+            };
+            let addr = BitcoinAddress::SegWit { network: BitcoinNetworkType::Mainnet, version, program };
+
+            let encoded = addr.to_bech32().unwrap();
+            let decoded = BitcoinAddress::from_bech32(&encoded).unwrap();
+            assert_eq!(decoded, addr);
+        }
+    }
+
+    #[test]
+    fn bech32_rejects_a_corrupted_checksum() {
+        let script = hex_bytes("00140be3e286a15ea85882761618e366586b5574100d").unwrap();
+        let addr = BitcoinAddress::from_scriptpubkey(BitcoinNetworkType::Mainnet, &script).unwrap();
+        let mut encoded = addr.to_bech32().unwrap();
+
+        // flip the last character, which lives entirely in the checksum.
+        let last = encoded.pop().unwrap();
+        let replacement = if last == 'q' { 'p' } else { 'q' };
+        encoded.push(replacement);
+
+        assert_eq!(BitcoinAddress::from_bech32(&encoded), None);
+    }
+
+    #[test]
+    fn legacy_addresses_have_no_bech32_encoding() {
+        let script = hex_bytes("76a9140be3e286a15ea85882761618e366586b5574100d88ac").unwrap();
+        let addr = BitcoinAddress::from_scriptpubkey(BitcoinNetworkType::Testnet, &script).unwrap();
+        assert_eq!(addr.to_bech32(), None);
+    }
+}
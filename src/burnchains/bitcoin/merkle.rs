@@ -0,0 +1,112 @@
+/*
+ copyright: (c) 2013-2018 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! SPV-style Merkle inclusion proofs for a single Bitcoin transaction, so a light client can
+//! validate a burn op against a header stream plus one proof, instead of fetching and parsing
+//! every transaction in the block it's in.
+
+use burnchains::bitcoin::blocks::BitcoinBlockParser;
+use burnchains::Txid;
+
+use util::hash::DoubleSha256;
+
+impl BitcoinBlockParser {
+    /// Verifies that the transaction `txid` is included, at position `tx_index`, under
+    /// `merkle_root` -- Bitcoin's standard Merkle branch check: starting from `txid`, each
+    /// sibling in `merkle_branch` is combined with the current hash (sibling first if the low
+    /// bit of the index is set, else current first), then the index is shifted right one bit
+    /// to move up a level. A single-transaction block has an empty branch, so `txid` itself
+    /// must equal `merkle_root`. If any bits remain set in the index once the branch is
+    /// exhausted, the caller claimed a `tx_index` wider than the tree the branch describes, so
+    /// the proof is rejected.
+    pub fn verify_tx_merkle_path(txid: &Txid, tx_index: u32, merkle_branch: &[Txid], merkle_root: &Txid) -> bool {
+        let mut current = [0u8; 32];
+        current.copy_from_slice(txid.as_bytes());
+        let mut index = tx_index;
+
+        for sibling in merkle_branch {
+            let mut buf = Vec::with_capacity(64);
+            if index & 1 == 1 {
+                buf.extend_from_slice(sibling.as_bytes());
+                buf.extend_from_slice(&current);
+            } else {
+                buf.extend_from_slice(&current);
+                buf.extend_from_slice(sibling.as_bytes());
+            }
+            current = DoubleSha256::from_data(&buf).0;
+            index >>= 1;
+        }
+
+        index == 0 && &current[..] == merkle_root.as_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn txid_of(b: u8) -> Txid {
+        Txid(DoubleSha256::from_data(&[b]).0)
+    }
+
+    fn combine(left: &Txid, right: &Txid) -> Txid {
+        let mut buf = Vec::with_capacity(64);
+        buf.extend_from_slice(left.as_bytes());
+        buf.extend_from_slice(right.as_bytes());
+        Txid(DoubleSha256::from_data(&buf).0)
+    }
+
+    #[test]
+    fn verifies_a_single_transaction_block_with_an_empty_branch() {
+        let txid = txid_of(1);
+        assert!(BitcoinBlockParser::verify_tx_merkle_path(&txid, 0, &[], &txid));
+    }
+
+    #[test]
+    fn verifies_a_four_leaf_tree_at_every_position() {
+        let leaves: Vec<Txid> = (1..=4u8).map(txid_of).collect();
+        let n01 = combine(&leaves[0], &leaves[1]);
+        let n23 = combine(&leaves[2], &leaves[3]);
+        let root = combine(&n01, &n23);
+
+        assert!(BitcoinBlockParser::verify_tx_merkle_path(&leaves[0], 0, &[leaves[1].clone(), n23.clone()], &root));
+        assert!(BitcoinBlockParser::verify_tx_merkle_path(&leaves[1], 1, &[leaves[0].clone(), n23.clone()], &root));
+        assert!(BitcoinBlockParser::verify_tx_merkle_path(&leaves[2], 2, &[leaves[3].clone(), n01.clone()], &root));
+        assert!(BitcoinBlockParser::verify_tx_merkle_path(&leaves[3], 3, &[leaves[2].clone(), n01.clone()], &root));
+    }
+
+    #[test]
+    fn rejects_a_branch_for_the_wrong_transaction() {
+        let leaves: Vec<Txid> = (1..=4u8).map(txid_of).collect();
+        let n01 = combine(&leaves[0], &leaves[1]);
+        let n23 = combine(&leaves[2], &leaves[3]);
+        let root = combine(&n01, &n23);
+
+        assert!(!BitcoinBlockParser::verify_tx_merkle_path(&txid_of(99), 0, &[leaves[1].clone(), n23.clone()], &root));
+    }
+
+    #[test]
+    fn rejects_a_tx_index_wider_than_the_branch_describes() {
+        let leaves: Vec<Txid> = (1..=2u8).map(txid_of).collect();
+        let root = combine(&leaves[0], &leaves[1]);
+
+        // tx_index=2 has a bit set beyond the single-level branch below
+        assert!(!BitcoinBlockParser::verify_tx_merkle_path(&leaves[0], 2, &[leaves[1].clone()], &root));
+    }
+}
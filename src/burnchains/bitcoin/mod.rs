@@ -0,0 +1,41 @@
+/*
+ copyright: (c) 2013-2018 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+pub mod address;
+pub mod rest;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitcoinNetworkType {
+    Mainnet,
+    Testnet,
+    Regtest,
+}
+
+impl BitcoinNetworkType {
+    /// The burn block height at which Blockstack consensus begins on this network. Mainnet and
+    /// testnet are pinned to the heights Blockstack actually launched at; regtest/devnet chains
+    /// are only ever a handful of blocks deep, so they start at genesis.
+    pub fn first_block_height(&self) -> u64 {
+        match self {
+            BitcoinNetworkType::Mainnet => 620_000,
+            BitcoinNetworkType::Testnet => 2_000_000,
+            BitcoinNetworkType::Regtest => 0,
+        }
+    }
+}
@@ -0,0 +1,241 @@
+/*
+ copyright: (c) 2013-2018 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Recovers a `BurnchainSigner` from a transaction input, whether it's funded by a legacy
+//! scriptSig or (for native SegWit inputs, which carry no scriptSig at all) a witness stack.
+//! `recover_signer_from_input` is what `parse_tx` calls per input; it dispatches to
+//! `recover_signer_from_script_sig` or `recover_signer_from_witness` depending on which of the
+//! two actually carries data. A P2PKH scriptSig or P2WPKH witness is the single-key case; a
+//! P2WSH witness carrying a bare-multisig redeem script is the multi-key case -- same shapes
+//! `BurnchainSigner` already models via `num_sigs`/`public_keys`, just sourced from a different
+//! part of the transaction.
+
+use burnchains::bitcoin::blocks::BitcoinBlockParser;
+use burnchains::{BurnchainSigner, PublicKey};
+
+use address::AddressHashMode;
+
+use chainstate::stacks::StacksPublicKey;
+
+const OP_1: u8 = 0x51;
+const OP_16: u8 = 0x60;
+const OP_CHECKMULTISIG: u8 = 0xae;
+
+impl BitcoinBlockParser {
+    /// Recovers the signer of a transaction input: tries `script_sig` first (a native SegWit
+    /// input leaves it empty), and falls back to `witness` otherwise. Returns `None` if neither
+    /// carries a recognized signer shape, the same way each underlying recovery path does for
+    /// its own input kind.
+    pub fn recover_signer_from_input(script_sig: &[u8], witness: &[Vec<u8>]) -> Option<BurnchainSigner> {
+        if !script_sig.is_empty() {
+            BitcoinBlockParser::recover_signer_from_script_sig(script_sig)
+        } else {
+            BitcoinBlockParser::recover_signer_from_witness(witness)
+        }
+    }
+
+    /// Recovers a single-key signer from a standard P2PKH scriptSig (`<signature> <pubkey>`,
+    /// each a direct push). Returns `None` for any other scriptSig shape, e.g. a bare-multisig
+    /// or P2SH redeem script this crate doesn't attribute a signer from.
+    fn recover_signer_from_script_sig(script_sig: &[u8]) -> Option<BurnchainSigner> {
+        let mut pos = 0;
+        let mut pushes = Vec::new();
+
+        while pos < script_sig.len() {
+            let push_len = script_sig[pos] as usize;
+            if push_len == 0 || push_len > 75 || pos + 1 + push_len > script_sig.len() {
+                return None;
+            }
+            pushes.push(&script_sig[pos + 1..pos + 1 + push_len]);
+            pos += 1 + push_len;
+        }
+
+        if pushes.len() != 2 {
+            return None;
+        }
+
+        let public_key = StacksPublicKey::from_slice(pushes[1]).ok()?;
+        Some(BurnchainSigner {
+            hash_mode: AddressHashMode::SerializeP2PKH,
+            num_sigs: 1,
+            public_keys: vec![public_key],
+        })
+    }
+
+    /// Recovers the signer of a SegWit input from its witness stack:
+    ///
+    /// - a 2-item witness (`<signature> <pubkey>`) is P2WPKH, a single-key signer;
+    /// - a 3-or-more-item witness (`<placeholder> <signature>... <redeem script>`) is P2WSH,
+    ///   and is only recognized here when the redeem script is a standard bare multisig
+    ///   (`OP_m <pubkey>... OP_n OP_CHECKMULTISIG`) -- any other witness program isn't
+    ///   attributable to a `BurnchainSigner`.
+    ///
+    /// Returns `None` for any other witness shape, the same way the legacy scriptSig path
+    /// returns no signers for a nonstandard scriptSig.
+    pub fn recover_signer_from_witness(witness: &[Vec<u8>]) -> Option<BurnchainSigner> {
+        match witness.len() {
+            2 => {
+                let public_key = StacksPublicKey::from_slice(&witness[1]).ok()?;
+                Some(BurnchainSigner {
+                    hash_mode: AddressHashMode::SerializeP2WPKH,
+                    num_sigs: 1,
+                    public_keys: vec![public_key],
+                })
+            },
+            n if n >= 3 => {
+                let redeem_script = &witness[n - 1];
+                let (num_sigs, public_keys) = BitcoinBlockParser::parse_multisig_redeem_script(redeem_script)?;
+                Some(BurnchainSigner {
+                    hash_mode: AddressHashMode::SerializeP2WSH,
+                    num_sigs,
+                    public_keys,
+                })
+            },
+            _ => None,
+        }
+    }
+
+    /// Parses a standard bare-multisig script (`OP_m <pubkey_1> ... <pubkey_n> OP_n
+    /// OP_CHECKMULTISIG`) into its threshold and ordered public keys. Each pubkey must be a
+    /// direct push (a single length byte followed by that many bytes); anything relying on
+    /// `OP_PUSHDATA1` or wider isn't a standard multisig script (no pubkey is ever that long).
+    fn parse_multisig_redeem_script(script: &[u8]) -> Option<(usize, Vec<StacksPublicKey>)> {
+        if script.len() < 3 {
+            return None;
+        }
+
+        let m_op = script[0];
+        if m_op < OP_1 || m_op > OP_16 {
+            return None;
+        }
+        let m = (m_op - OP_1 + 1) as usize;
+
+        let mut pos = 1;
+        let mut public_keys = Vec::new();
+
+        loop {
+            if pos >= script.len() {
+                return None;
+            }
+
+            let op = script[pos];
+
+            if op >= OP_1 && op <= OP_16 {
+                let n = (op - OP_1 + 1) as usize;
+                if n != public_keys.len() || pos + 2 != script.len() || script[pos + 1] != OP_CHECKMULTISIG {
+                    return None;
+                }
+                return Some((m, public_keys));
+            }
+
+            let push_len = op as usize;
+            if push_len == 0 || push_len > 75 || pos + 1 + push_len > script.len() {
+                return None;
+            }
+
+            let public_key = StacksPublicKey::from_slice(&script[pos + 1..pos + 1 + push_len]).ok()?;
+            public_keys.push(public_key);
+            pos += 1 + push_len;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::hash::hex_bytes;
+
+    const PUBKEY_1: &str = "02d8015134d9db8178ac93acbc43170a2f20febba5087a5b0437058765ad5133d0";
+    const PUBKEY_2: &str = "03984286096373539ae529bd997c92792d4e5b5967be72979a42f587a625394116";
+
+    #[test]
+    fn recovers_a_single_key_signer_from_a_p2wpkh_witness() {
+        let sig = vec![0x30, 0x44];
+        let pubkey_bytes = hex_bytes(PUBKEY_1).unwrap();
+        let witness = vec![sig, pubkey_bytes.clone()];
+
+        let signer = BitcoinBlockParser::recover_signer_from_witness(&witness).unwrap();
+        assert_eq!(signer.hash_mode, AddressHashMode::SerializeP2WPKH);
+        assert_eq!(signer.num_sigs, 1);
+        assert_eq!(signer.public_keys, vec![StacksPublicKey::from_slice(&pubkey_bytes).unwrap()]);
+    }
+
+    #[test]
+    fn recovers_a_multisig_signer_from_a_p2wsh_witness() {
+        let pk1 = hex_bytes(PUBKEY_1).unwrap();
+        let pk2 = hex_bytes(PUBKEY_2).unwrap();
+
+        let mut redeem_script = vec![OP_1 + 1]; // OP_2 (2-of-2)
+        redeem_script.push(pk1.len() as u8);
+        redeem_script.extend_from_slice(&pk1);
+        redeem_script.push(pk2.len() as u8);
+        redeem_script.extend_from_slice(&pk2);
+        redeem_script.push(OP_1 + 1); // OP_2 (2 keys total)
+        redeem_script.push(OP_CHECKMULTISIG);
+
+        let witness = vec![vec![], vec![0x30, 0x44], vec![0x30, 0x45], redeem_script];
+
+        let signer = BitcoinBlockParser::recover_signer_from_witness(&witness).unwrap();
+        assert_eq!(signer.hash_mode, AddressHashMode::SerializeP2WSH);
+        assert_eq!(signer.num_sigs, 2);
+        assert_eq!(signer.public_keys, vec![
+            StacksPublicKey::from_slice(&pk1).unwrap(),
+            StacksPublicKey::from_slice(&pk2).unwrap(),
+        ]);
+    }
+
+    #[test]
+    fn recover_signer_from_input_uses_the_script_sig_when_present() {
+        let sig = vec![0x30, 0x44];
+        let pubkey_bytes = hex_bytes(PUBKEY_1).unwrap();
+
+        let mut script_sig = vec![sig.len() as u8];
+        script_sig.extend_from_slice(&sig);
+        script_sig.push(pubkey_bytes.len() as u8);
+        script_sig.extend_from_slice(&pubkey_bytes);
+
+        let signer = BitcoinBlockParser::recover_signer_from_input(&script_sig, &[]).unwrap();
+        assert_eq!(signer.hash_mode, AddressHashMode::SerializeP2PKH);
+        assert_eq!(signer.num_sigs, 1);
+        assert_eq!(signer.public_keys, vec![StacksPublicKey::from_slice(&pubkey_bytes).unwrap()]);
+    }
+
+    #[test]
+    fn recover_signer_from_input_falls_back_to_the_witness_when_the_script_sig_is_empty() {
+        let sig = vec![0x30, 0x44];
+        let pubkey_bytes = hex_bytes(PUBKEY_1).unwrap();
+        let witness = vec![sig, pubkey_bytes.clone()];
+
+        let signer = BitcoinBlockParser::recover_signer_from_input(&[], &witness).unwrap();
+        assert_eq!(signer.hash_mode, AddressHashMode::SerializeP2WPKH);
+        assert_eq!(signer.public_keys, vec![StacksPublicKey::from_slice(&pubkey_bytes).unwrap()]);
+    }
+
+    #[test]
+    fn rejects_a_witness_program_that_is_not_a_standard_multisig() {
+        let witness = vec![vec![], vec![0x30, 0x44], vec![0xab, 0xcd, 0xef]];
+        assert_eq!(BitcoinBlockParser::recover_signer_from_witness(&witness), None);
+    }
+
+    #[test]
+    fn rejects_an_empty_or_single_item_witness() {
+        assert_eq!(BitcoinBlockParser::recover_signer_from_witness(&[]), None);
+        assert_eq!(BitcoinBlockParser::recover_signer_from_witness(&[vec![0x01]]), None);
+    }
+}
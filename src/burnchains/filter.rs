@@ -0,0 +1,297 @@
+/*
+ copyright: (c) 2013-2018 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! BIP158-style Golomb-Coded Set (GCS) filters, keyed by `BurnchainHeaderHash`, so a light
+//! node can test whether a burnchain block plausibly contains a tagged Blockstack operation
+//! (by OP_RETURN magic+opcode prefix, or by a funding script) without downloading the block
+//! and running `BlockstackOperationType::from_tx` over every tx in it.
+
+use burnchains::BurnchainHeaderHash;
+
+/// Golomb-Rice parameter: the number of low bits of each difference stored as the remainder.
+const GCS_P: u8 = 19;
+/// Inverse of the false-positive rate (about 1/M), per BIP158 (M = 1.497137 * 2^P, rounded).
+const GCS_M: u64 = 784931;
+
+pub struct GCSFilter {
+    /// number of items committed to the filter
+    n: u64,
+    /// Golomb-Rice-encoded, sorted differences between successive hashed item values
+    encoded: Vec<u8>,
+}
+
+/// Minimal SipHash-2-4, used only to map filter items into the range [0, N*M) -- this is not
+/// a general-purpose hasher, and the key is derived from the burn header hash, not secret.
+struct SipHasher24 {
+    k0: u64,
+    k1: u64,
+}
+
+impl SipHasher24 {
+    fn new(burn_header_hash: &BurnchainHeaderHash) -> SipHasher24 {
+        let bytes = burn_header_hash.as_bytes();
+        let k0 = u64::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]]);
+        let k1 = u64::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11], bytes[12], bytes[13], bytes[14], bytes[15]]);
+        SipHasher24 { k0, k1 }
+    }
+
+    fn hash(&self, data: &[u8]) -> u64 {
+        let mut v0: u64 = 0x736f6d6570736575 ^ self.k0;
+        let mut v1: u64 = 0x646f72616e646f6d ^ self.k1;
+        let mut v2: u64 = 0x6c7967656e657261 ^ self.k0;
+        let mut v3: u64 = 0x7465646279746573 ^ self.k1;
+
+        macro_rules! sipround {
+            () => {
+                v0 = v0.wrapping_add(v1); v1 = v1.rotate_left(13); v1 ^= v0; v0 = v0.rotate_left(32);
+                v2 = v2.wrapping_add(v3); v3 = v3.rotate_left(16); v3 ^= v2;
+                v0 = v0.wrapping_add(v3); v3 = v3.rotate_left(21); v3 ^= v0;
+                v2 = v2.wrapping_add(v1); v1 = v1.rotate_left(17); v1 ^= v2; v2 = v2.rotate_left(32);
+            }
+        }
+
+        let len = data.len();
+        let end = len - (len % 8);
+        let mut i = 0;
+        while i < end {
+            let mi = u64::from_le_bytes([
+                data[i], data[i+1], data[i+2], data[i+3],
+                data[i+4], data[i+5], data[i+6], data[i+7]
+            ]);
+            v3 ^= mi;
+            sipround!();
+            sipround!();
+            v0 ^= mi;
+            i += 8;
+        }
+
+        let mut last_block = [0u8; 8];
+        last_block[..(len - end)].copy_from_slice(&data[end..]);
+        last_block[7] = (len as u8) & 0xff;
+        let mi = u64::from_le_bytes(last_block);
+        v3 ^= mi;
+        sipround!();
+        sipround!();
+        v0 ^= mi;
+
+        v2 ^= 0xff;
+        sipround!();
+        sipround!();
+        sipround!();
+        sipround!();
+
+        v0 ^ v1 ^ v2 ^ v3
+    }
+}
+
+// Maps a hashed item into a uniform value in [0, n*m) via `(siphash(item) * n*m) >> 64`.
+fn hash_to_range(hasher: &SipHasher24, item: &[u8], n: u64, m: u64) -> u64 {
+    let h = hasher.hash(item) as u128;
+    let nm = (n as u128) * (m as u128);
+    ((h * nm) >> 64) as u64
+}
+
+struct BitWriter {
+    bits: Vec<u8>,
+    cur_byte: u8,
+    cur_len: u8,
+}
+
+impl BitWriter {
+    fn new() -> BitWriter {
+        BitWriter { bits: vec![], cur_byte: 0, cur_len: 0 }
+    }
+
+    fn write_bit(&mut self, bit: bool) {
+        if bit {
+            self.cur_byte |= 1 << (7 - self.cur_len);
+        }
+        self.cur_len += 1;
+        if self.cur_len == 8 {
+            self.bits.push(self.cur_byte);
+            self.cur_byte = 0;
+            self.cur_len = 0;
+        }
+    }
+
+    fn write_bits(&mut self, mut value: u64, num_bits: u8) {
+        for i in (0..num_bits).rev() {
+            self.write_bit((value >> i) & 1 == 1);
+        }
+        let _ = &mut value;
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.cur_len > 0 {
+            self.bits.push(self.cur_byte);
+        }
+        self.bits
+    }
+}
+
+struct BitReader<'a> {
+    bits: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(bits: &'a [u8]) -> BitReader<'a> {
+        BitReader { bits, pos: 0 }
+    }
+
+    fn read_bit(&mut self) -> Option<bool> {
+        let byte_idx = self.pos / 8;
+        if byte_idx >= self.bits.len() {
+            return None;
+        }
+        let bit_idx = 7 - (self.pos % 8);
+        self.pos += 1;
+        Some((self.bits[byte_idx] >> bit_idx) & 1 == 1)
+    }
+
+    fn read_bits(&mut self, num_bits: u8) -> Option<u64> {
+        let mut value: u64 = 0;
+        for _ in 0..num_bits {
+            value = (value << 1) | (self.read_bit()? as u64);
+        }
+        Some(value)
+    }
+}
+
+fn golomb_rice_encode(writer: &mut BitWriter, value: u64, p: u8) {
+    let quotient = value >> p;
+    for _ in 0..quotient {
+        writer.write_bit(true);
+    }
+    writer.write_bit(false);
+    writer.write_bits(value & ((1u64 << p) - 1), p);
+}
+
+fn golomb_rice_decode(reader: &mut BitReader, p: u8) -> Option<u64> {
+    let mut quotient: u64 = 0;
+    loop {
+        match reader.read_bit()? {
+            true => quotient += 1,
+            false => break,
+        }
+    }
+    let remainder = reader.read_bits(p)?;
+    Some((quotient << p) | remainder)
+}
+
+impl GCSFilter {
+    /// Builds a GCS filter over `items` (raw byte strings -- e.g. an OP_RETURN magic+opcode
+    /// prefix, or a funding scriptPubKey), keyed by the burn block's header hash.
+    pub fn build(burn_header_hash: &BurnchainHeaderHash, items: &[Vec<u8>]) -> GCSFilter {
+        let n = items.len() as u64;
+        let hasher = SipHasher24::new(burn_header_hash);
+
+        let mut values: Vec<u64> = items.iter()
+            .map(|item| hash_to_range(&hasher, item, n, GCS_M))
+            .collect();
+        values.sort();
+
+        let mut writer = BitWriter::new();
+        let mut last_value: u64 = 0;
+        for value in values {
+            golomb_rice_encode(&mut writer, value - last_value, GCS_P);
+            last_value = value;
+        }
+
+        GCSFilter {
+            n,
+            encoded: writer.finish(),
+        }
+    }
+
+    /// Tests whether `item` is plausibly a member of the filter. False positives are possible
+    /// (rate ~= 1/M); false negatives are not.
+    pub fn match_filter(&self, burn_header_hash: &BurnchainHeaderHash, item: &[u8]) -> bool {
+        if self.n == 0 {
+            return false;
+        }
+
+        let hasher = SipHasher24::new(burn_header_hash);
+        let target = hash_to_range(&hasher, item, self.n, GCS_M);
+
+        let mut reader = BitReader::new(&self.encoded);
+        let mut running_sum: u64 = 0;
+        for _ in 0..self.n {
+            let delta = match golomb_rice_decode(&mut reader, GCS_P) {
+                Some(d) => d,
+                None => return false,
+            };
+            running_sum += delta;
+            if running_sum == target {
+                return true;
+            }
+            if running_sum > target {
+                return false;
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_header_hash(byte: u8) -> BurnchainHeaderHash {
+        BurnchainHeaderHash([byte; 32])
+    }
+
+    #[test]
+    fn filter_matches_committed_items() {
+        let header_hash = test_header_hash(0x11);
+        let items: Vec<Vec<u8>> = vec![
+            b"id-leader-key-register".to_vec(),
+            b"id-leader-block-commit".to_vec(),
+            b"id-user-burn-support".to_vec(),
+        ];
+
+        let filter = GCSFilter::build(&header_hash, &items);
+
+        for item in items.iter() {
+            assert!(filter.match_filter(&header_hash, item));
+        }
+    }
+
+    #[test]
+    fn empty_filter_matches_nothing() {
+        let header_hash = test_header_hash(0x22);
+        let filter = GCSFilter::build(&header_hash, &[]);
+        assert!(!filter.match_filter(&header_hash, b"anything"));
+    }
+
+    #[test]
+    fn golomb_rice_roundtrips() {
+        let mut writer = BitWriter::new();
+        let values = vec![0u64, 1, 2, 127, 128, 1_000_000];
+        for v in values.iter() {
+            golomb_rice_encode(&mut writer, *v, GCS_P);
+        }
+        let bits = writer.finish();
+
+        let mut reader = BitReader::new(&bits);
+        for v in values.iter() {
+            assert_eq!(*v, golomb_rice_decode(&mut reader, GCS_P).unwrap());
+        }
+    }
+}
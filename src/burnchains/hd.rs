@@ -0,0 +1,135 @@
+/*
+ copyright: (c) 2013-2018 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Deterministic derivation of a miner's burnchain signing key and VRF prover key from a single
+//! master seed, built on the generic `util::bip32` recurrence. Rather than storing one secret
+//! per `LeaderKeyRegisterOp`/`LeaderBlockCommitOp`, an operator keeps only the seed and derives
+//! a fresh key per fork segment/epoch on demand -- so rotating keys is a matter of advancing the
+//! derivation path, not generating and backing up new secrets.
+
+use util::bip32::{ExtendedPrivateKey, DerivationPath, ChildNumber, Bip32Error};
+
+use address::AddressHashMode;
+use burnchains::BurnchainSigner;
+use chainstate::stacks::StacksPublicKey;
+use util::vrf::VRFPrivateKey;
+
+/// This project's own BIP32 purpose constant for burn-signing key rings -- not a SLIP-44
+/// registered value, just a fixed hardened first path component so a seed used for burn signing
+/// can't collide with a derivation path used for anything else.
+const BURN_SIGNING_PURPOSE: u32 = 5183;
+
+/// A miner's burn-signing key ring: a single master seed from which every fork-segment/epoch's
+/// signing key and VRF prover key are derived on demand via `m/purpose'/fork_segment_id'/epoch_num`.
+pub struct BurnKeyRing {
+    master: ExtendedPrivateKey,
+}
+
+impl BurnKeyRing {
+    pub fn from_seed(seed: &[u8]) -> Result<BurnKeyRing, Bip32Error> {
+        Ok(BurnKeyRing { master: ExtendedPrivateKey::master(seed)? })
+    }
+
+    /// The recommended path for a given fork segment and epoch: `m/purpose'/fork_segment_id'/epoch_num`.
+    /// The fork segment is hardened since it partitions the key space between forks that should
+    /// never share a signing key; the epoch number is left unhardened so (if ever needed) a
+    /// watch-only public key for a fork segment could derive its per-epoch keys without the
+    /// private key.
+    pub fn recommended_path(fork_segment_id: u64, epoch_num: u16) -> DerivationPath {
+        DerivationPath::new(vec![
+            ChildNumber::Hardened(BURN_SIGNING_PURPOSE),
+            ChildNumber::Hardened(fork_segment_id as u32),
+            ChildNumber::Normal(epoch_num as u32),
+        ])
+    }
+
+    /// Derives the single-key `BurnchainSigner` a `LeaderBlockCommitOp`/`LeaderKeyRegisterOp`'s
+    /// `input`/`address` would be built from at `path`.
+    pub fn derive_burn_signer(&self, path: &DerivationPath, hash_mode: AddressHashMode) -> Result<BurnchainSigner, Bip32Error> {
+        let child = self.master.derive_path(path)?;
+        let public_key_bytes = child.public_key().serialize();
+        let public_key = StacksPublicKey::from_slice(&public_key_bytes)
+            .map_err(|_| Bip32Error::InvalidSeedLength)?;
+
+        Ok(BurnchainSigner {
+            hash_mode,
+            num_sigs: 1,
+            public_keys: vec![public_key],
+        })
+    }
+
+    /// Derives the VRF prover key used to register a `LeaderKeyRegisterOp` and to prove the
+    /// `new_seed` of the `LeaderBlockCommitOp`s that key backs, at `path`. The key's scalar is
+    /// the derived child's private-key bytes, reused as-is -- a BIP32 private key and a VRF
+    /// prover key are both just a uniformly-random 32-byte scalar, so no further derivation step
+    /// is needed between them.
+    pub fn derive_vrf_key(&self, path: &DerivationPath) -> Result<VRFPrivateKey, Bip32Error> {
+        let child = self.master.derive_path(path)?;
+        VRFPrivateKey::from_bytes(&child.private_key[..]).ok_or(Bip32Error::InvalidSeedLength)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::hash::hex_bytes;
+    use util::vrf::VRFPublicKey;
+
+    fn seed() -> Vec<u8> {
+        hex_bytes("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f").unwrap()
+    }
+
+    #[test]
+    fn deriving_the_same_path_twice_yields_the_same_signer() {
+        let ring = BurnKeyRing::from_seed(&seed()).unwrap();
+        let path = BurnKeyRing::recommended_path(3, 12);
+
+        let a = ring.derive_burn_signer(&path, AddressHashMode::SerializeP2PKH).unwrap();
+        let b = ring.derive_burn_signer(&path, AddressHashMode::SerializeP2PKH).unwrap();
+        assert_eq!(a.public_keys, b.public_keys);
+        assert_eq!(a.num_sigs, 1);
+        assert_eq!(a.hash_mode, AddressHashMode::SerializeP2PKH);
+    }
+
+    #[test]
+    fn different_epochs_in_the_same_fork_segment_yield_different_signers() {
+        let ring = BurnKeyRing::from_seed(&seed()).unwrap();
+        let a = ring.derive_burn_signer(&BurnKeyRing::recommended_path(3, 12), AddressHashMode::SerializeP2PKH).unwrap();
+        let b = ring.derive_burn_signer(&BurnKeyRing::recommended_path(3, 13), AddressHashMode::SerializeP2PKH).unwrap();
+        assert_ne!(a.public_keys, b.public_keys);
+    }
+
+    #[test]
+    fn different_fork_segments_at_the_same_epoch_yield_different_signers() {
+        let ring = BurnKeyRing::from_seed(&seed()).unwrap();
+        let a = ring.derive_burn_signer(&BurnKeyRing::recommended_path(3, 12), AddressHashMode::SerializeP2PKH).unwrap();
+        let b = ring.derive_burn_signer(&BurnKeyRing::recommended_path(4, 12), AddressHashMode::SerializeP2PKH).unwrap();
+        assert_ne!(a.public_keys, b.public_keys);
+    }
+
+    #[test]
+    fn the_derived_vrf_key_is_deterministic_and_its_public_key_matches() {
+        let ring = BurnKeyRing::from_seed(&seed()).unwrap();
+        let path = BurnKeyRing::recommended_path(3, 12);
+
+        let a = ring.derive_vrf_key(&path).unwrap();
+        let b = ring.derive_vrf_key(&path).unwrap();
+        assert_eq!(VRFPublicKey::from_private(&a), VRFPublicKey::from_private(&b));
+    }
+}
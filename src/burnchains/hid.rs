@@ -0,0 +1,80 @@
+/*
+ copyright: (c) 2013-2018 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! The raw USB HID transport `burnchains::signing::HidBurnSigningDevice` talks over. This is
+//! deliberately thin: it knows nothing about burn ops, preimages, or `AddressHashMode` -- it
+//! only enumerates HID devices, runs the vendor-agnostic lock/unlock/get-public-key/sign report
+//! exchange, and hands back raw bytes. All burn-op-specific shaping of those bytes lives in
+//! `burnchains::signing`.
+
+use deps::hid as hidapi;
+
+/// A single connected HID device, opened and ready for report exchange.
+pub struct HidDevice {
+    handle: hidapi::DeviceHandle,
+}
+
+/// A transport-level failure -- the device was unplugged, a report timed out, or the OS denied
+/// access. Distinct from the device *answering* with a rejection, which callers see as a normal
+/// `Ok` report and interpret themselves (e.g. `submit_unlock_response` returning `Ok(false)`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HidTransportError(pub String);
+
+impl From<hidapi::HidError> for HidTransportError {
+    fn from(e: hidapi::HidError) -> HidTransportError {
+        HidTransportError(e.to_string())
+    }
+}
+
+impl HidDevice {
+    /// Enumerates and opens every connected device that answers the expected vendor/product
+    /// report format. An empty result means no device is plugged in -- not a transport error --
+    /// so callers (`HidBurnSigningDevice::enumerate`) are the ones who turn that into
+    /// `BurnSigningError::DeviceNotFound`.
+    pub fn enumerate() -> Result<Vec<HidDevice>, HidTransportError> {
+        let handles = hidapi::enumerate()?;
+        handles.into_iter()
+            .map(|info| hidapi::open(&info).map(|handle| HidDevice { handle }).map_err(HidTransportError::from))
+            .collect()
+    }
+
+    /// Asks the device whether it's locked; if so, opens an unlock session and returns its
+    /// handle for the caller to round-trip back into `submit_unlock_response`.
+    pub fn open_unlock_session(&self) -> Result<super::signing::DeviceSessionHandle, HidTransportError> {
+        let session_id = self.handle.begin_unlock_session()?;
+        Ok(super::signing::DeviceSessionHandle(session_id))
+    }
+
+    /// Submits a PIN/passphrase response for a previously-opened unlock session. Returns
+    /// `Ok(true)` if the device accepted it and is now unlocked, `Ok(false)` if the device
+    /// rejected it (wrong PIN, expired session); only an actual transport failure is `Err`.
+    pub fn submit_unlock_response(&self, session: &super::signing::DeviceSessionHandle, response: &str) -> Result<bool, HidTransportError> {
+        self.handle.submit_unlock_response(&session.0, response).map_err(HidTransportError::from)
+    }
+
+    /// Fetches the device's public key(s) for its currently-configured account/derivation path.
+    pub fn get_public_keys(&self) -> Result<Vec<Vec<u8>>, HidTransportError> {
+        self.handle.get_public_keys().map_err(HidTransportError::from)
+    }
+
+    /// Requests a signature over `preimage` from the device's currently-configured key(s).
+    pub fn sign(&self, preimage: &[u8]) -> Result<Vec<Vec<u8>>, HidTransportError> {
+        self.handle.sign(preimage).map_err(HidTransportError::from)
+    }
+}
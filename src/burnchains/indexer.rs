@@ -0,0 +1,268 @@
+/*
+ copyright: (c) 2013-2018 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! A pluggable `BlockSource` abstraction for the burnchain indexer: the indexer drives header
+//! sync and reorg detection against whatever `BlockSource` it's configured with (a full node's
+//! RPC/REST interface, a light-client header source, etc.), rather than being hard-wired to a
+//! single backend.
+
+use burnchains::BurnchainHeaderHash;
+use chainstate::burn::operations::Error as op_error;
+
+/// One burnchain block header, as seen by a `BlockSource`. Only the fields the indexer needs
+/// to detect and resolve reorgs are modeled here -- backend-specific fields (e.g. a Bitcoin
+/// header's bits/nonce) are the `BlockSource` impl's concern, not the indexer's.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BurnchainHeader {
+    pub block_height: u64,
+    pub block_hash: BurnchainHeaderHash,
+    pub parent_block_hash: BurnchainHeaderHash,
+}
+
+/// A source of burnchain headers and blocks. Implementations range from a full node queried
+/// over RPC/REST to a light client that only ever sees headers.
+pub trait BlockSource {
+    /// The backend's current chain-tip height, i.e. the highest height it can serve a header
+    /// for right now.
+    fn get_chain_tip_height(&self) -> Result<u64, op_error>;
+
+    /// Fetches the header at the given height, if the backend has one.
+    fn get_header_at(&self, height: u64) -> Result<Option<BurnchainHeader>, op_error>;
+}
+
+/// Errors raised by the indexer itself, as distinct from `op_error`, which covers validity of
+/// the burn ops a block *contains*. These are about whether the indexer can see the header
+/// chain it needs at all.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The indexer's anchor header -- the header we last synced up to, or the network's
+    /// configured first block -- is absent from the backend. In regtest/devnet this happens
+    /// legitimately when the chain is reset and rewound below where we last were; callers
+    /// should treat it as recoverable (re-anchor and resync) rather than a fatal condition.
+    MissingHeaders,
+    /// The backend itself failed to answer.
+    Backend(op_error),
+}
+
+impl From<op_error> for Error {
+    fn from(e: op_error) -> Error {
+        Error::Backend(e)
+    }
+}
+
+/// Walks a `BlockSource`'s headers from `last_known_height`/`last_known_hash` up to its
+/// current tip, detecting reorgs along the way: if the header now reported at a height we
+/// previously processed no longer matches what we saw before, we've forked, and the caller
+/// needs to roll back to the fork point before applying new headers.
+pub struct HeaderSync<'a> {
+    source: &'a dyn BlockSource,
+    /// The network's configured first block height: headers below this are never expected to
+    /// exist, so their absence isn't a `MissingHeaders` condition.
+    first_block_height: u64,
+}
+
+/// The result of walking a `BlockSource` forward from a known tip.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SyncResult {
+    /// No new headers beyond what we already had.
+    UpToDate,
+    /// New headers extending directly from `last_known_hash`, in height order.
+    NewHeaders(Vec<BurnchainHeader>),
+    /// The header at `fork_height` no longer matches `expected_hash` -- the backend has
+    /// reorged, and the caller must roll back to (at least) `fork_height - 1` before
+    /// re-syncing.
+    Reorg { fork_height: u64, expected_hash: BurnchainHeaderHash },
+}
+
+impl<'a> HeaderSync<'a> {
+    pub fn new(source: &'a dyn BlockSource, first_block_height: u64) -> HeaderSync<'a> {
+        HeaderSync { source, first_block_height }
+    }
+
+    pub fn sync_from(&self, last_known_height: u64, last_known_hash: &BurnchainHeaderHash) -> Result<SyncResult, Error> {
+        let tip_height = self.source.get_chain_tip_height()?;
+
+        if tip_height < last_known_height {
+            // the backend is behind us -- nothing to do until it catches up, and it isn't a
+            // reorg unless/until it reports a different header at a height we've already seen
+            return Ok(SyncResult::UpToDate);
+        }
+
+        // re-check the header we already had, in case the backend reorged at or below our tip
+        match self.source.get_header_at(last_known_height)? {
+            Some(known_header) => {
+                if &known_header.block_hash != last_known_hash {
+                    return Ok(SyncResult::Reorg {
+                        fork_height: last_known_height,
+                        expected_hash: known_header.block_hash,
+                    });
+                }
+            },
+            None if last_known_height >= self.first_block_height => {
+                // we've synced past this height before, so the backend losing it entirely
+                // (rather than just reorging it) means its chain was reset out from under us
+                return Err(Error::MissingHeaders);
+            },
+            None => { /* below the configured first block -- nothing to anchor against yet */ },
+        }
+
+        if tip_height == last_known_height {
+            return Ok(SyncResult::UpToDate);
+        }
+
+        let mut new_headers = Vec::with_capacity((tip_height - last_known_height) as usize);
+        let mut parent_hash = last_known_hash.clone();
+
+        for height in (last_known_height + 1)..=tip_height {
+            let header = match self.source.get_header_at(height)? {
+                Some(h) => h,
+                None => break,
+            };
+
+            if header.parent_block_hash != parent_hash {
+                return Ok(SyncResult::Reorg {
+                    fork_height: height,
+                    expected_hash: header.parent_block_hash,
+                });
+            }
+
+            parent_hash = header.block_hash.clone();
+            new_headers.push(header);
+        }
+
+        if new_headers.is_empty() {
+            Ok(SyncResult::UpToDate)
+        } else {
+            Ok(SyncResult::NewHeaders(new_headers))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    struct MockBlockSource {
+        headers: HashMap<u64, BurnchainHeader>,
+        tip_height: u64,
+    }
+
+    impl BlockSource for MockBlockSource {
+        fn get_chain_tip_height(&self) -> Result<u64, op_error> {
+            Ok(self.tip_height)
+        }
+
+        fn get_header_at(&self, height: u64) -> Result<Option<BurnchainHeader>, op_error> {
+            Ok(self.headers.get(&height).cloned())
+        }
+    }
+
+    fn hash(byte: u8) -> BurnchainHeaderHash {
+        BurnchainHeaderHash([byte; 32])
+    }
+
+    fn chain(n: u64) -> HashMap<u64, BurnchainHeader> {
+        let mut headers = HashMap::new();
+        for height in 0..=n {
+            headers.insert(height, BurnchainHeader {
+                block_height: height,
+                block_hash: hash(height as u8),
+                parent_block_hash: if height == 0 { hash(0) } else { hash((height - 1) as u8) },
+            });
+        }
+        headers
+    }
+
+    #[test]
+    fn reports_up_to_date_when_nothing_new() {
+        let source = MockBlockSource { headers: chain(5), tip_height: 5 };
+        let sync = HeaderSync::new(&source, 0);
+        let result = sync.sync_from(5, &hash(5)).unwrap();
+        assert_eq!(result, SyncResult::UpToDate);
+    }
+
+    #[test]
+    fn returns_new_headers_extending_the_known_tip() {
+        let source = MockBlockSource { headers: chain(7), tip_height: 7 };
+        let sync = HeaderSync::new(&source, 0);
+        match sync.sync_from(5, &hash(5)).unwrap() {
+            SyncResult::NewHeaders(headers) => {
+                assert_eq!(headers.len(), 2);
+                assert_eq!(headers[0].block_height, 6);
+                assert_eq!(headers[1].block_height, 7);
+            },
+            other => assert!(false, "expected NewHeaders, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn detects_a_reorg_at_the_known_tip() {
+        let mut headers = chain(5);
+        headers.insert(5, BurnchainHeader { block_height: 5, block_hash: hash(0xff), parent_block_hash: hash(4) });
+        let source = MockBlockSource { headers, tip_height: 5 };
+        let sync = HeaderSync::new(&source, 0);
+
+        match sync.sync_from(5, &hash(5)).unwrap() {
+            SyncResult::Reorg { fork_height, expected_hash } => {
+                assert_eq!(fork_height, 5);
+                assert_eq!(expected_hash, hash(0xff));
+            },
+            other => assert!(false, "expected Reorg, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn detects_a_reorg_among_new_headers() {
+        let mut headers = chain(7);
+        // height 7 no longer builds off of our chain's height-6 hash
+        headers.insert(7, BurnchainHeader { block_height: 7, block_hash: hash(7), parent_block_hash: hash(0xee) });
+        let source = MockBlockSource { headers, tip_height: 7 };
+        let sync = HeaderSync::new(&source, 0);
+
+        match sync.sync_from(5, &hash(5)).unwrap() {
+            SyncResult::Reorg { fork_height, expected_hash } => {
+                assert_eq!(fork_height, 7);
+                assert_eq!(expected_hash, hash(0xee));
+            },
+            other => assert!(false, "expected Reorg, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reports_missing_headers_when_the_anchor_header_is_gone() {
+        // the backend still has a tip at height 5, but has lost the header we last anchored
+        // on -- e.g. a devnet chain that got reset and rewound
+        let mut headers = chain(5);
+        headers.remove(&5);
+        let source = MockBlockSource { headers, tip_height: 5 };
+        let sync = HeaderSync::new(&source, 0);
+
+        assert_eq!(sync.sync_from(5, &hash(5)), Err(Error::MissingHeaders));
+    }
+
+    #[test]
+    fn does_not_treat_a_missing_header_below_the_first_block_height_as_an_error() {
+        let source = MockBlockSource { headers: HashMap::new(), tip_height: 10 };
+        let sync = HeaderSync::new(&source, 8);
+
+        // we've never seen height 3 because the network's chain doesn't start until height 8
+        assert_eq!(sync.sync_from(3, &hash(3)), Ok(SyncResult::UpToDate));
+    }
+}
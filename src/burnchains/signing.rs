@@ -0,0 +1,251 @@
+/*
+ copyright: (c) 2013-2018 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! A `BurnSigningDevice` abstraction for producing burn-op signatures from keys that never
+//! leave an external hardware wallet (Trezor/Ledger-style), as an alternative to the in-process
+//! `new_from_secrets` helpers each op type exposes for tests. A miner signs by handing a device
+//! the exact canonical preimage (`LeaderBlockCommitOp::preimage`/`LeaderKeyRegisterOp::preimage`)
+//! that ends up on the burnchain -- never a re-derived or re-serialized copy of it -- so what the
+//! user confirms on-device is byte-identical to what lands on-chain. `check()` stays agnostic to
+//! all of this: it only ever sees the resulting `BurnchainSigner`'s public keys/num_sigs shape,
+//! the same as it does for an in-process signer.
+
+use burnchains::BurnchainSigner;
+use burnchains::hid::{HidDevice, HidTransportError};
+
+use address::AddressHashMode;
+
+use chainstate::stacks::StacksPublicKey;
+
+/// A handle to an in-progress unlock challenge on a locked device. Opaque to everything but the
+/// transport that issued it -- a caller only ever round-trips it back into `unlock`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceSessionHandle(pub String);
+
+/// Errors raised while talking to a signing device, as distinct from `op_error`, which covers
+/// the validity of the op the device is being asked to sign.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BurnSigningError {
+    /// No device answered enumeration.
+    DeviceNotFound,
+    /// The device is locked behind a PIN/passphrase; `session` must be carried back into
+    /// `unlock` along with the user's response before signing can proceed.
+    DeviceLocked(DeviceSessionHandle),
+    /// The unlock response the caller supplied was rejected by the device.
+    UnlockRejected,
+    /// The USB HID transport itself failed (device unplugged mid-exchange, malformed report,
+    /// etc.) -- not a judgment about the signing request.
+    TransportError(String),
+    /// The device returned a public key or signature this code couldn't parse.
+    InvalidResponse(String),
+}
+
+impl From<HidTransportError> for BurnSigningError {
+    fn from(e: HidTransportError) -> BurnSigningError {
+        BurnSigningError::TransportError(e.0)
+    }
+}
+
+/// A signing device capable of producing a `BurnchainSigner` and signing the canonical preimage
+/// of a burn op with the key(s) it holds. Implemented here over USB HID (`HidBurnSigningDevice`),
+/// but kept as a trait so an in-process or RPC-backed signer could satisfy it too.
+pub trait BurnSigningDevice {
+    /// Returns the signer this device would produce for `hash_mode`, without touching the
+    /// network -- i.e. the device's already-derived public key(s), reshaped into the
+    /// `BurnchainSigner` a burn op's `input`/`public_key` field expects.
+    ///
+    /// Returns `Err(BurnSigningError::DeviceLocked(..))` if the device needs a PIN/passphrase
+    /// before it will disclose its public key(s); the caller should collect the response from
+    /// the user and retry via `unlock`.
+    fn signer(&self, hash_mode: AddressHashMode) -> Result<BurnchainSigner, BurnSigningError>;
+
+    /// Submits a PIN/passphrase response for the session a prior call reported as
+    /// `DeviceLocked`. On success, subsequent calls to `signer`/`sign` no longer return
+    /// `DeviceLocked` for this device.
+    fn unlock(&mut self, session: DeviceSessionHandle, response: &str) -> Result<(), BurnSigningError>;
+
+    /// Requests a signature for `preimage` -- the exact bytes `LeaderBlockCommitOp::preimage`/
+    /// `LeaderKeyRegisterOp::preimage` produced, unmodified -- from the key(s) backing
+    /// `signer(hash_mode)`. Returns one signature per key, in the same order as
+    /// `signer(hash_mode).public_keys`.
+    fn sign(&self, hash_mode: AddressHashMode, preimage: &[u8]) -> Result<Vec<Vec<u8>>, BurnSigningError>;
+}
+
+/// A `BurnSigningDevice` backed by a Trezor/Ledger-style hardware wallet connected over USB HID.
+pub struct HidBurnSigningDevice {
+    device: HidDevice,
+    locked: bool,
+}
+
+impl HidBurnSigningDevice {
+    /// Enumerates connected devices over USB HID and wraps each one that answers. Does not
+    /// unlock or query public keys yet -- a freshly-enumerated device is treated as locked until
+    /// a call to `unlock` succeeds. `signer`/`sign` do not ask the device for its lock state:
+    /// while the device is treated as locked they open a new unlock session and return
+    /// `DeviceLocked`.
+    pub fn enumerate() -> Result<Vec<HidBurnSigningDevice>, BurnSigningError> {
+        let devices = HidDevice::enumerate()?;
+        if devices.is_empty() {
+            return Err(BurnSigningError::DeviceNotFound);
+        }
+
+        Ok(devices.into_iter().map(|device| HidBurnSigningDevice { device, locked: true }).collect())
+    }
+
+    fn require_unlocked(&self) -> Result<(), BurnSigningError> {
+        if self.locked {
+            let session = self.device.open_unlock_session()?;
+            return Err(BurnSigningError::DeviceLocked(session));
+        }
+        Ok(())
+    }
+}
+
+impl BurnSigningDevice for HidBurnSigningDevice {
+    fn signer(&self, hash_mode: AddressHashMode) -> Result<BurnchainSigner, BurnSigningError> {
+        self.require_unlocked()?;
+
+        let public_key_bytes = self.device.get_public_keys()?;
+        let public_keys = public_key_bytes.iter()
+            .map(|bytes| StacksPublicKey::from_slice(bytes).map_err(|_| BurnSigningError::InvalidResponse("malformed public key".to_string())))
+            .collect::<Result<Vec<StacksPublicKey>, BurnSigningError>>()?;
+
+        Ok(BurnchainSigner {
+            hash_mode,
+            num_sigs: public_keys.len(),
+            public_keys,
+        })
+    }
+
+    fn unlock(&mut self, session: DeviceSessionHandle, response: &str) -> Result<(), BurnSigningError> {
+        if self.device.submit_unlock_response(&session, response)? {
+            self.locked = false;
+            Ok(())
+        } else {
+            Err(BurnSigningError::UnlockRejected)
+        }
+    }
+
+    fn sign(&self, hash_mode: AddressHashMode, preimage: &[u8]) -> Result<Vec<Vec<u8>>, BurnSigningError> {
+        self.require_unlocked()?;
+
+        let num_sigs = self.signer(hash_mode)?.num_sigs;
+        let signatures = self.device.sign(preimage)?;
+        if signatures.len() != num_sigs {
+            return Err(BurnSigningError::InvalidResponse(format!(
+                "device returned {} signatures, expected {}", signatures.len(), num_sigs
+            )));
+        }
+
+        Ok(signatures)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// A fake HID device for exercising the lock/unlock/sign flow without real hardware. Lives
+    /// only in this test module -- production code always goes through `HidDevice`.
+    struct FakeDevice {
+        locked: RefCell<bool>,
+        public_key: StacksPublicKey,
+        expected_response: &'static str,
+    }
+
+    impl FakeDevice {
+        fn signing_device(&self) -> FakeBurnSigningDevice {
+            FakeBurnSigningDevice { device: self, locked: *self.locked.borrow() }
+        }
+    }
+
+    struct FakeBurnSigningDevice<'a> {
+        device: &'a FakeDevice,
+        locked: bool,
+    }
+
+    impl<'a> BurnSigningDevice for FakeBurnSigningDevice<'a> {
+        fn signer(&self, hash_mode: AddressHashMode) -> Result<BurnchainSigner, BurnSigningError> {
+            if self.locked {
+                return Err(BurnSigningError::DeviceLocked(DeviceSessionHandle("fake-session".to_string())));
+            }
+            Ok(BurnchainSigner {
+                hash_mode,
+                num_sigs: 1,
+                public_keys: vec![self.device.public_key.clone()],
+            })
+        }
+
+        fn unlock(&mut self, _session: DeviceSessionHandle, response: &str) -> Result<(), BurnSigningError> {
+            if response == self.device.expected_response {
+                self.locked = false;
+                *self.device.locked.borrow_mut() = false;
+                Ok(())
+            } else {
+                Err(BurnSigningError::UnlockRejected)
+            }
+        }
+
+        fn sign(&self, hash_mode: AddressHashMode, preimage: &[u8]) -> Result<Vec<Vec<u8>>, BurnSigningError> {
+            self.signer(hash_mode)?;
+            Ok(vec![preimage.to_vec()])
+        }
+    }
+
+    fn fake_pubkey() -> StacksPublicKey {
+        StacksPublicKey::from_hex("02d8015134d9db8178ac93acbc43170a2f20febba5087a5b0437058765ad5133d0").unwrap()
+    }
+
+    #[test]
+    fn a_locked_device_reports_device_locked_instead_of_a_signer() {
+        let device = FakeDevice { locked: RefCell::new(true), public_key: fake_pubkey(), expected_response: "1234" };
+        let signing_device = device.signing_device();
+
+        assert_eq!(
+            signing_device.signer(AddressHashMode::SerializeP2PKH),
+            Err(BurnSigningError::DeviceLocked(DeviceSessionHandle("fake-session".to_string()))),
+        );
+    }
+
+    #[test]
+    fn unlocking_with_the_right_response_allows_signer_and_sign_to_proceed() {
+        let device = FakeDevice { locked: RefCell::new(true), public_key: fake_pubkey(), expected_response: "1234" };
+        let mut signing_device = device.signing_device();
+
+        signing_device.unlock(DeviceSessionHandle("fake-session".to_string()), "1234").unwrap();
+
+        let signer = signing_device.signer(AddressHashMode::SerializeP2PKH).unwrap();
+        assert_eq!(signer.num_sigs, 1);
+        assert_eq!(signer.public_keys, vec![fake_pubkey()]);
+
+        let preimage = vec![0xde, 0xad, 0xbe, 0xef];
+        let signatures = signing_device.sign(AddressHashMode::SerializeP2PKH, &preimage).unwrap();
+        assert_eq!(signatures, vec![preimage]);
+    }
+
+    #[test]
+    fn unlocking_with_the_wrong_response_is_rejected_and_leaves_the_device_locked() {
+        let device = FakeDevice { locked: RefCell::new(true), public_key: fake_pubkey(), expected_response: "1234" };
+        let mut signing_device = device.signing_device();
+
+        assert_eq!(signing_device.unlock(DeviceSessionHandle("fake-session".to_string()), "0000"), Err(BurnSigningError::UnlockRejected));
+        assert!(signing_device.signer(AddressHashMode::SerializeP2PKH).is_err());
+    }
+}
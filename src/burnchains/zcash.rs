@@ -0,0 +1,509 @@
+/*
+ copyright: (c) 2013-2018 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Zcash as an alternative burnchain. Zcash transactions extend the Bitcoin transaction
+//! format with an (optional) transparent part plus Sprout/Sapling shielded value pools --
+//! Blockstack operations can only ever live in the transparent part (an OP_RETURN output is
+//! not representable inside a shielded pool), so a shielded-only tx can be recognized and
+//! skipped up front without attempting to interpret any of its shielded fields.
+//!
+//! NOTE on scope: this module parses the transparent envelope and detects shielded-component
+//! presence, and exposes that through the same `get_signers`/`get_recipients`/`data`/`opcode`
+//! shape `BlockstackOperation::from_tx` consumes elsewhere. It stops short of wiring an actual
+//! `BurnchainTransaction::Zcash` variant, because `BurnchainTransaction` (and, for that matter,
+//! `parse_tx`'s own `BurnchainTransaction::Bitcoin` wiring) isn't defined anywhere in this tree
+//! -- that enum lives outside this snapshot. Plumbing a new variant through it is blocked on
+//! that type existing, not on anything Zcash-specific.
+
+use burnchains::{BurnchainSigner, BurnchainRecipient, Txid, BurnchainHeaderHash};
+use burnchains::bitcoin::BitcoinNetworkType;
+use burnchains::bitcoin::blocks::BitcoinBlockParser;
+use burnchains::bitcoin::address::BitcoinAddress;
+use chainstate::stacks::StacksAddress;
+use util::hash::DoubleSha256;
+
+/// The subset of a parsed Zcash transaction that Blockstack ops care about: its transparent
+/// inputs and outputs, plus whether it also carries shielded (Sprout/Sapling) components.
+/// Shielded fields themselves (note commitments, nullifiers, proofs) are opaque to us -- we
+/// only need to know they're present so we can tell a shielded-only tx apart from one with no
+/// burnchain-observable effect at all.
+pub struct ZcashTransaction {
+    txid: Txid,
+    vtxindex: usize,
+    transparent_inputs: Vec<BurnchainSigner>,
+    transparent_outputs: Vec<BurnchainRecipient>,
+    has_shielded_spends: bool,
+    has_shielded_outputs: bool,
+    opcode: u8,
+    data: Vec<u8>,
+}
+
+// A tx with no transparent outputs cannot carry a Blockstack OP_RETURN payload, and a tx with
+// no transparent inputs cannot fund/sign a Blockstack op -- in both cases, this tx is either
+// purely shielded or purely coinbase/internal, and `BlockstackOperation::from_tx` has nothing
+// to parse out of it. Pulled out as a free function over plain counts so the classification
+// logic is testable without needing to construct a real signer/recipient.
+fn classify(num_transparent_inputs: usize, num_transparent_outputs: usize, has_shielded: bool) -> (bool, bool) {
+    let is_transparent = num_transparent_inputs > 0 && num_transparent_outputs > 0;
+    let is_shielded_only = has_shielded && !is_transparent;
+    (is_transparent, is_shielded_only)
+}
+
+impl ZcashTransaction {
+    pub fn is_transparent(&self) -> bool {
+        classify(self.transparent_inputs.len(), self.transparent_outputs.len(),
+                 self.has_shielded_spends || self.has_shielded_outputs).0
+    }
+
+    pub fn is_shielded_only(&self) -> bool {
+        classify(self.transparent_inputs.len(), self.transparent_outputs.len(),
+                 self.has_shielded_spends || self.has_shielded_outputs).1
+    }
+
+    /// The signers recovered from this tx's transparent inputs, in input order -- mirrors
+    /// `BurnchainTransaction::get_signers()` so `BlockstackOperation::parse_from_tx` can treat
+    /// a `ZcashTransaction` the same way it treats a Bitcoin one.
+    pub fn get_signers(&self) -> Vec<BurnchainSigner> {
+        self.transparent_inputs.clone()
+    }
+
+    /// The recipients recovered from this tx's transparent outputs, in output order, excluding
+    /// whichever output (if any) carries the Blockstack OP_RETURN payload -- mirrors
+    /// `BurnchainTransaction::get_recipients()`.
+    pub fn get_recipients(&self) -> Vec<BurnchainRecipient> {
+        self.transparent_outputs.clone()
+    }
+
+    /// The Blockstack opcode byte carried in this tx's OP_RETURN payload, or `0` if this tx
+    /// carries no recognized Blockstack payload at all.
+    pub fn opcode(&self) -> u8 {
+        self.opcode
+    }
+
+    /// The bytes following the opcode in this tx's OP_RETURN payload.
+    pub fn data(&self) -> Vec<u8> {
+        self.data.clone()
+    }
+
+    pub fn txid(&self) -> Txid {
+        self.txid.clone()
+    }
+
+    pub fn vtxindex(&self) -> usize {
+        self.vtxindex
+    }
+}
+
+/// Parses the Overwinter/Sapling transaction envelope just far enough to separate the
+/// transparent part (which parses exactly like a Bitcoin transaction) from the presence of
+/// shielded components, without attempting to deserialize shielded proof data.
+pub struct ZcashBlockParser {
+    network: BitcoinNetworkType,
+    /// Overwinter/Sapling version group ID that this parser expects to see; txs with an
+    /// unrecognized version group are treated as unparseable rather than crashing the parser.
+    expected_version_group_id: u32,
+    /// The 2-byte magic a transparent output's OP_RETURN payload must start with to be
+    /// recognized as a Blockstack op, rather than some other application's OP_RETURN data.
+    magic: [u8; 2],
+}
+
+const OP_RETURN: u8 = 0x6a;
+
+/// Bitcoin's own rule for a single-push OP_RETURN output: `OP_RETURN <pushlen> <data>`, where
+/// `pushlen` is a direct (non-CompactSize) byte count up to 75 -- the same ceiling the scriptSig
+/// push parsing in `burnchains::bitcoin::witness` uses for its own direct-push lengths.
+/// Blockstack payloads are always small enough to fit a single minimal push.
+const MAX_SINGLE_PUSH_LEN: usize = 75;
+
+impl ZcashBlockParser {
+    pub fn new(network: BitcoinNetworkType, expected_version_group_id: u32, magic: [u8; 2]) -> ZcashBlockParser {
+        ZcashBlockParser { network, expected_version_group_id, magic }
+    }
+
+    pub fn accepts_version_group(&self, version_group_id: u32) -> bool {
+        version_group_id == self.expected_version_group_id
+    }
+
+    /// Parses a raw Zcash transaction's transparent envelope (Sprout v1/v2, Overwinter v3, or
+    /// Sapling v4), recovering its signers/recipients/OP_RETURN payload and noting whether it
+    /// also touches the shielded pools -- without decoding any shielded field's contents. This
+    /// is the Zcash-side counterpart to a Bitcoin `parse_tx`: it returns `None` on anything
+    /// that doesn't parse as a well-formed envelope (truncated bytes, an unrecognized version
+    /// group) rather than guessing.
+    pub fn parse_tx(&self, txid: Txid, vtxindex: usize, tx_bytes: &[u8]) -> Option<ZcashTransaction> {
+        let mut pos = 0usize;
+
+        let header = read_u32_le(tx_bytes, &mut pos)?;
+        let f_overwintered = header & 0x8000_0000 != 0;
+        let version = header & 0x7fff_ffff;
+
+        if f_overwintered {
+            let version_group_id = read_u32_le(tx_bytes, &mut pos)?;
+            if !self.accepts_version_group(version_group_id) {
+                return None;
+            }
+        }
+
+        let num_inputs = read_compact_size(tx_bytes, &mut pos)? as usize;
+        let mut transparent_inputs = Vec::with_capacity(num_inputs);
+        for _ in 0..num_inputs {
+            let script_sig = read_transparent_input(tx_bytes, &mut pos)?;
+            // Zcash's transparent pool predates Bitcoin's segwit fork, so a transparent input
+            // is always funded by a scriptSig, never a witness stack.
+            let signer = BitcoinBlockParser::recover_signer_from_input(&script_sig, &[])?;
+            transparent_inputs.push(signer);
+        }
+
+        let num_outputs = read_compact_size(tx_bytes, &mut pos)? as usize;
+        let mut transparent_outputs = Vec::new();
+        let mut opcode = 0u8;
+        let mut data = Vec::new();
+        for _ in 0..num_outputs {
+            let (value, script_pubkey) = read_transparent_output(tx_bytes, &mut pos)?;
+
+            if let Some((parsed_opcode, parsed_data)) = self.parse_op_return_payload(&script_pubkey) {
+                opcode = parsed_opcode;
+                data = parsed_data;
+                continue;
+            }
+
+            if let Some(btc_address) = BitcoinAddress::from_scriptpubkey(self.network, &script_pubkey) {
+                transparent_outputs.push(BurnchainRecipient {
+                    address: StacksAddress::from_bitcoin_address(&btc_address),
+                    amount: value,
+                });
+            }
+            // a nonstandard, non-OP_RETURN scriptPubKey can't be attributed to a recipient, so
+            // it's silently uncounted here, same as `BitcoinAddress::from_scriptpubkey`'s own
+            // `None` case is elsewhere.
+        }
+
+        read_u32_le(tx_bytes, &mut pos)?; // lock_time
+
+        if f_overwintered {
+            read_u32_le(tx_bytes, &mut pos)?; // nExpiryHeight, Overwinter (v3) and up
+        }
+
+        let mut has_shielded_spends = false;
+        let mut has_shielded_outputs = false;
+
+        if f_overwintered && version >= 4 {
+            read_u64_le(tx_bytes, &mut pos)?; // valueBalance
+
+            let num_shielded_spends = read_compact_size(tx_bytes, &mut pos)? as usize;
+            advance(tx_bytes, &mut pos, num_shielded_spends.checked_mul(SAPLING_SPEND_DESCRIPTION_LEN)?)?;
+            has_shielded_spends |= num_shielded_spends > 0;
+
+            let num_shielded_outputs = read_compact_size(tx_bytes, &mut pos)? as usize;
+            advance(tx_bytes, &mut pos, num_shielded_outputs.checked_mul(SAPLING_OUTPUT_DESCRIPTION_LEN)?)?;
+            has_shielded_outputs |= num_shielded_outputs > 0;
+        }
+
+        // Sprout joinsplits (version 2 and up, with or without Overwinter/Sapling) are the
+        // final section of the envelope, so we only need their count to know whether this tx
+        // touches the Sprout pool at all -- not to decode the joinsplit descriptions
+        // themselves, whose proof system (PHGR13 pre-Sapling, Groth16 from Sapling on) is
+        // version-dependent and irrelevant to anything else in this tx's transparent envelope.
+        if version >= 2 {
+            let num_joinsplits = read_compact_size(tx_bytes, &mut pos)? as usize;
+            if num_joinsplits > 0 {
+                has_shielded_spends = true;
+                has_shielded_outputs = true;
+            }
+        }
+
+        Some(ZcashTransaction {
+            txid,
+            vtxindex,
+            transparent_inputs,
+            transparent_outputs,
+            has_shielded_spends,
+            has_shielded_outputs,
+            opcode,
+            data,
+        })
+    }
+
+    /// Recognizes `OP_RETURN <pushlen> <2-byte magic> <opcode> <data...>` and extracts the
+    /// opcode/data pair, so an OP_RETURN output belonging to some other application (wrong
+    /// magic, or not an OP_RETURN at all) is silently treated as a plain transparent output
+    /// rather than misread as a Blockstack payload.
+    fn parse_op_return_payload(&self, script: &[u8]) -> Option<(u8, Vec<u8>)> {
+        if script.len() < 2 || script[0] != OP_RETURN {
+            return None;
+        }
+
+        let push_len = script[1] as usize;
+        if push_len == 0 || push_len > MAX_SINGLE_PUSH_LEN || script.len() != 2 + push_len {
+            return None;
+        }
+
+        let payload = &script[2..2 + push_len];
+        if payload.len() < 3 || payload[0..2] != self.magic {
+            return None;
+        }
+
+        Some((payload[2], payload[3..].to_vec()))
+    }
+}
+
+/// cv(32) + anchor(32) + nullifier(32) + rk(32) + zkproof(192) + spendAuthSig(64)
+const SAPLING_SPEND_DESCRIPTION_LEN: usize = 384;
+/// cv(32) + cmu(32) + ephemeralKey(32) + encCiphertext(580) + outCiphertext(80) + zkproof(192)
+const SAPLING_OUTPUT_DESCRIPTION_LEN: usize = 948;
+
+fn advance(bytes: &[u8], pos: &mut usize, n: usize) -> Option<()> {
+    let end = pos.checked_add(n)?;
+    if end > bytes.len() {
+        return None;
+    }
+    *pos = end;
+    Some(())
+}
+
+fn read_bytes<'a>(bytes: &'a [u8], pos: &mut usize, n: usize) -> Option<&'a [u8]> {
+    let start = *pos;
+    advance(bytes, pos, n)?;
+    Some(&bytes[start..*pos])
+}
+
+fn read_u8(bytes: &[u8], pos: &mut usize) -> Option<u8> {
+    let slice = read_bytes(bytes, pos, 1)?;
+    Some(slice[0])
+}
+
+fn read_u32_le(bytes: &[u8], pos: &mut usize) -> Option<u32> {
+    let slice = read_bytes(bytes, pos, 4)?;
+    Some(u32::from_le_bytes([slice[0], slice[1], slice[2], slice[3]]))
+}
+
+fn read_u64_le(bytes: &[u8], pos: &mut usize) -> Option<u64> {
+    let slice = read_bytes(bytes, pos, 8)?;
+    Some(u64::from_le_bytes([
+        slice[0], slice[1], slice[2], slice[3], slice[4], slice[5], slice[6], slice[7],
+    ]))
+}
+
+/// Bitcoin-style CompactSize: values below `0xfd` are encoded directly; `0xfd`/`0xfe`/`0xff`
+/// prefix a following 2/4/8-byte little-endian value. Zcash reuses this encoding unchanged for
+/// every count-prefixed field in the transparent envelope (inputs, outputs, scripts) and the
+/// shielded-pool description arrays.
+fn read_compact_size(bytes: &[u8], pos: &mut usize) -> Option<u64> {
+    let marker = read_u8(bytes, pos)?;
+    match marker {
+        0..=0xfc => Some(marker as u64),
+        0xfd => {
+            let slice = read_bytes(bytes, pos, 2)?;
+            Some(u16::from_le_bytes([slice[0], slice[1]]) as u64)
+        },
+        0xfe => Some(read_u32_le(bytes, pos)? as u64),
+        0xff => read_u64_le(bytes, pos),
+    }
+}
+
+/// Reads one transparent input (`prevout: {txid, index}`, scriptSig, sequence) and returns its
+/// scriptSig, the only part a signer can be recovered from.
+fn read_transparent_input(bytes: &[u8], pos: &mut usize) -> Option<Vec<u8>> {
+    read_bytes(bytes, pos, 32)?; // prevout txid
+    read_u32_le(bytes, pos)?; // prevout index
+    let script_len = read_compact_size(bytes, pos)? as usize;
+    let script_sig = read_bytes(bytes, pos, script_len)?.to_vec();
+    read_u32_le(bytes, pos)?; // sequence
+    Some(script_sig)
+}
+
+/// Reads one transparent output (value + scriptPubKey).
+fn read_transparent_output(bytes: &[u8], pos: &mut usize) -> Option<(u64, Vec<u8>)> {
+    let value = read_u64_le(bytes, pos)?;
+    let script_len = read_compact_size(bytes, pos)? as usize;
+    let script_pubkey = read_bytes(bytes, pos, script_len)?.to_vec();
+    Some((value, script_pubkey))
+}
+
+/// Hashes a Zcash block header, tolerating its variable length: unlike Bitcoin's fixed 80-byte
+/// header, a Zcash header appends a 32-byte `hashFinalSaplingRoot`, widens `nNonce` to 32
+/// bytes, and ends in a CompactSize-prefixed variable-length Equihash solution -- so the end of
+/// the header has to be found by walking the solution's own length prefix, rather than assumed
+/// at a fixed offset the way `rest.rs`'s Bitcoin header hashing can. Follows this repo's own
+/// `rest.rs::double_sha256` precedent of hashing the digest bytes directly, with no reversal.
+pub fn zcash_block_header_hash(header_bytes: &[u8]) -> Option<BurnchainHeaderHash> {
+    let mut pos = 0usize;
+    advance(header_bytes, &mut pos, 4)?; // nVersion
+    advance(header_bytes, &mut pos, 32)?; // hashPrevBlock
+    advance(header_bytes, &mut pos, 32)?; // hashMerkleRoot
+    advance(header_bytes, &mut pos, 32)?; // hashFinalSaplingRoot
+    advance(header_bytes, &mut pos, 4)?; // nTime
+    advance(header_bytes, &mut pos, 4)?; // nBits
+    advance(header_bytes, &mut pos, 32)?; // nNonce (32 bytes, unlike Bitcoin's 4)
+
+    let solution_len = read_compact_size(header_bytes, &mut pos)? as usize;
+    advance(header_bytes, &mut pos, solution_len)?; // Equihash solution
+
+    let header_span = &header_bytes[0..pos];
+    Some(BurnchainHeaderHash(DoubleSha256::from_data(header_span).0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::hash::hex_bytes;
+
+    #[test]
+    fn shielded_only_tx_is_not_transparent() {
+        let (is_transparent, is_shielded_only) = classify(0, 0, true);
+        assert!(is_shielded_only);
+        assert!(!is_transparent);
+    }
+
+    #[test]
+    fn mixed_tx_is_transparent_even_with_shielded_components() {
+        let (is_transparent, is_shielded_only) = classify(1, 1, true);
+        assert!(is_transparent);
+        assert!(!is_shielded_only);
+    }
+
+    #[test]
+    fn fully_transparent_tx_has_no_shielded_components() {
+        let (is_transparent, is_shielded_only) = classify(1, 1, false);
+        assert!(is_transparent);
+        assert!(!is_shielded_only);
+    }
+
+    #[test]
+    fn version_group_filter_rejects_unknown_groups() {
+        let parser = ZcashBlockParser::new(BitcoinNetworkType::Testnet, 0x892f2085, *b"id");
+        assert!(parser.accepts_version_group(0x892f2085));
+        assert!(!parser.accepts_version_group(0x0000_0001));
+    }
+
+    fn compact_size(n: u64) -> Vec<u8> {
+        if n < 0xfd {
+            vec![n as u8]
+        } else if n <= 0xffff {
+            let mut v = vec![0xfd];
+            v.extend_from_slice(&(n as u16).to_le_bytes());
+            v
+        } else {
+            panic!("test helper only covers small counts");
+        }
+    }
+
+    // Builds a minimal, well-formed Sapling (v4) tx with one P2PKH input, one P2PKH output,
+    // and one OP_RETURN output carrying a Blockstack-shaped payload -- just enough to exercise
+    // every fixed-size field `parse_tx` walks through, without needing a real chain fixture.
+    fn sample_sapling_tx_bytes() -> Vec<u8> {
+        let mut tx = Vec::new();
+        tx.extend_from_slice(&(0x8000_0004u32).to_le_bytes()); // fOverwintered | version 4
+        tx.extend_from_slice(&0x892f_2085u32.to_le_bytes()); // Sapling version group id
+
+        tx.extend_from_slice(&compact_size(1)); // 1 input
+        tx.extend_from_slice(&[0x11; 32]); // prevout txid
+        tx.extend_from_slice(&0u32.to_le_bytes()); // prevout index
+        let script_sig = hex_bytes("4730440220111111111111111111111111111111111111111111111111111111111111111102201111111111111111111111111111111111111111111111111111111111111111012102d8015134d9db8178ac93acbc43170a2f20febba5087a5b0437058765ad5133d0").unwrap();
+        tx.extend_from_slice(&compact_size(script_sig.len() as u64));
+        tx.extend_from_slice(&script_sig);
+        tx.extend_from_slice(&0xffffffffu32.to_le_bytes()); // sequence
+
+        tx.extend_from_slice(&compact_size(2)); // 2 outputs
+        tx.extend_from_slice(&1000u64.to_le_bytes());
+        let script_pubkey = hex_bytes("76a9140be3e286a15ea85882761618e366586b5574100d88ac").unwrap();
+        tx.extend_from_slice(&compact_size(script_pubkey.len() as u64));
+        tx.extend_from_slice(&script_pubkey);
+
+        tx.extend_from_slice(&0u64.to_le_bytes());
+        let mut op_return_payload = vec![b'i', b'd', 42u8];
+        op_return_payload.extend_from_slice(b"hello");
+        let mut op_return_script = vec![OP_RETURN, op_return_payload.len() as u8];
+        op_return_script.extend_from_slice(&op_return_payload);
+        tx.extend_from_slice(&compact_size(op_return_script.len() as u64));
+        tx.extend_from_slice(&op_return_script);
+
+        tx.extend_from_slice(&0u32.to_le_bytes()); // lock_time
+        tx.extend_from_slice(&0u32.to_le_bytes()); // nExpiryHeight
+
+        tx.extend_from_slice(&0u64.to_le_bytes()); // valueBalance
+        tx.extend_from_slice(&compact_size(0)); // 0 shielded spends
+        tx.extend_from_slice(&compact_size(0)); // 0 shielded outputs
+        tx.extend_from_slice(&compact_size(0)); // 0 joinsplits
+
+        tx
+    }
+
+    #[test]
+    fn parses_transparent_part_and_op_return_payload_of_a_sapling_tx() {
+        let parser = ZcashBlockParser::new(BitcoinNetworkType::Testnet, 0x892f_2085, *b"id");
+        let tx_bytes = sample_sapling_tx_bytes();
+        let txid = Txid([0x22; 32]);
+
+        let tx = parser.parse_tx(txid.clone(), 5, &tx_bytes).expect("well-formed fixture should parse");
+
+        assert_eq!(tx.txid(), txid);
+        assert_eq!(tx.vtxindex(), 5);
+        assert_eq!(tx.get_signers().len(), 1);
+        assert_eq!(tx.get_recipients().len(), 1);
+        assert_eq!(tx.get_recipients()[0].amount, 1000);
+        assert_eq!(tx.opcode(), 42u8);
+        assert_eq!(tx.data(), b"hello".to_vec());
+        assert!(tx.is_transparent());
+        assert!(!tx.is_shielded_only());
+    }
+
+    #[test]
+    fn rejects_a_tx_with_an_unexpected_version_group() {
+        let parser = ZcashBlockParser::new(BitcoinNetworkType::Testnet, 0xdead_beef, *b"id");
+        let tx_bytes = sample_sapling_tx_bytes();
+        assert!(parser.parse_tx(Txid([0u8; 32]), 0, &tx_bytes).is_none());
+    }
+
+    #[test]
+    fn ignores_an_op_return_payload_with_the_wrong_magic() {
+        let parser = ZcashBlockParser::new(BitcoinNetworkType::Testnet, 0x892f_2085, *b"xy");
+        let tx_bytes = sample_sapling_tx_bytes();
+        let tx = parser.parse_tx(Txid([0u8; 32]), 0, &tx_bytes).unwrap();
+        assert_eq!(tx.opcode(), 0);
+        assert_eq!(tx.data(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn zcash_header_hash_tolerates_a_nonzero_length_equihash_solution() {
+        let mut header = Vec::new();
+        header.extend_from_slice(&[0u8; 4]); // nVersion
+        header.extend_from_slice(&[0u8; 32]); // hashPrevBlock
+        header.extend_from_slice(&[0u8; 32]); // hashMerkleRoot
+        header.extend_from_slice(&[0u8; 32]); // hashFinalSaplingRoot
+        header.extend_from_slice(&[0u8; 4]); // nTime
+        header.extend_from_slice(&[0u8; 4]); // nBits
+        header.extend_from_slice(&[0u8; 32]); // nNonce
+        header.extend_from_slice(&compact_size(3));
+        header.extend_from_slice(&[0xaa, 0xbb, 0xcc]); // Equihash solution
+        header.push(0xff); // trailing byte belonging to whatever follows the header, not to it
+
+        let hash = zcash_block_header_hash(&header).expect("well-formed header should hash");
+        let expected = DoubleSha256::from_data(&header[0..header.len() - 1]);
+        assert_eq!(hash.0, expected.0);
+    }
+
+    #[test]
+    fn zcash_header_hash_is_none_on_a_truncated_solution() {
+        let mut header = vec![0u8; 4 + 32 + 32 + 32 + 4 + 4 + 32];
+        header.push(0xfd); // claims a 2-byte CompactSize length follows
+        header.push(0x01); // but only one byte is actually present
+        assert!(zcash_block_header_hash(&header).is_none());
+    }
+}
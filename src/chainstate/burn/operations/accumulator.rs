@@ -0,0 +1,314 @@
+/*
+ copyright: (c) 2013-2018 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! A pruned-UTXO-style accumulator for unspent `LeaderKeyRegisterOp`s, so a node doesn't have
+//! to keep every leader key ever registered around just to prove one is unused.
+//!
+//! Per fork segment, the set of unspent keys is a forest of perfect Merkle tree roots (the
+//! same shape a Merkle mountain range uses): `record_leader_key` appends a new key as a
+//! height-0 leaf and repeatedly merges it with the current rightmost root of equal height,
+//! so the forest is always just `O(log n)` roots. `consume_leader_key` spends a key by
+//! checking an inclusion proof against one of those roots, then deleting the leaf: a deleted
+//! leaf's siblings along its path can no longer be combined back up to the original root, so
+//! each one is promoted to a new standalone root at its own height -- the root the leaf lived
+//! under is replaced by its proof's sibling hashes, not a compacted tree.
+//!
+//! `build_proof_from_table` is the compatibility path for a node that still keeps the full
+//! leader-key table: it replays every registered key for a fork segment to rebuild the forest
+//! from scratch and produce a proof for one of them. The replayed forest is the unpruned one,
+//! so such a proof only verifies against roots from which no key has been consumed.
+
+use chainstate::burn::operations::Error as op_error;
+use chainstate::burn::operations::LeaderKeyRegisterOp;
+
+use chainstate::burn::db::burndb::BurnDB;
+
+use burnchains::Address;
+
+use util::db::DBTx;
+use util::hash::DoubleSha256;
+
+/// An inclusion proof that a leader key is present (and thus provable-unspent) under one of a
+/// fork segment's accumulator roots: the sibling hash at each level of the leaf's path, from
+/// the leaf upward, plus which root it lives under and where in that root's subtree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeaderKeyAccumulatorProof {
+    pub root_index: usize,
+    pub leaf_index: u64,
+    pub siblings: Vec<[u8; 32]>,
+}
+
+/// Hashes the fields that make a leader key registration unique: the consensus hash and VRF
+/// public key it bound together, its memo, the address it can be spent by, and the exact
+/// position (`block_height`, `vtxindex`) it was confirmed at. Including the position means two
+/// otherwise-identical registrations at different positions still hash to distinct leaves.
+pub fn leaf_hash(key: &LeaderKeyRegisterOp) -> [u8; 32] {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(key.consensus_hash.as_bytes());
+    bytes.extend_from_slice(&key.public_key.as_bytes());
+    bytes.extend_from_slice(&key.memo);
+    bytes.extend_from_slice(&key.address.to_bytes());
+    bytes.extend_from_slice(&key.block_height.to_be_bytes());
+    bytes.extend_from_slice(&key.vtxindex.to_be_bytes());
+    DoubleSha256::from_data(&bytes).0
+}
+
+fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut bytes = Vec::with_capacity(64);
+    bytes.extend_from_slice(left);
+    bytes.extend_from_slice(right);
+    DoubleSha256::from_data(&bytes).0
+}
+
+/// Appends `leaf` to the forest as a new height-0 root, then repeatedly merges the two
+/// rightmost roots while they're the same height -- the same carry-propagation a binary
+/// counter uses. While nothing has been pruned, the forest has at most one root per height and
+/// its size tracks `log2` of the number of leaves inserted; `verify_and_prune` appends the
+/// promoted siblings at the end, after which several roots may share a height and only the
+/// rightmost ones are merged.
+pub fn insert_leaf(roots: &mut Vec<(u8, [u8; 32])>, leaf: [u8; 32]) {
+    let mut height = 0u8;
+    let mut hash = leaf;
+
+    while roots.last().map(|(h, _)| *h) == Some(height) {
+        let (_, left) = roots.pop().unwrap();
+        hash = hash_pair(&left, &hash);
+        height += 1;
+    }
+
+    roots.push((height, hash));
+}
+
+/// Verifies that `leaf` is included under `roots[proof.root_index]` by walking `proof.siblings`
+/// from the leaf upward, then deletes it: the root it lived under is removed, and each sibling
+/// along its path is promoted to a new standalone root at its own height (it no longer has a
+/// partner to combine with, since that partner -- our leaf's side of the tree -- is gone).
+pub fn verify_and_prune(roots: &mut Vec<(u8, [u8; 32])>, leaf: [u8; 32], proof: &LeaderKeyAccumulatorProof) -> Result<(), op_error> {
+    let (height, stored_root) = *roots.get(proof.root_index).ok_or(op_error::LeaderKeyAccumulatorProofInvalid)?;
+
+    if proof.siblings.len() != height as usize {
+        return Err(op_error::LeaderKeyAccumulatorProofInvalid);
+    }
+
+    let mut cur = leaf;
+    for (level, sibling) in proof.siblings.iter().enumerate() {
+        let bit = (proof.leaf_index >> level) & 1;
+        cur = if bit == 0 { hash_pair(&cur, sibling) } else { hash_pair(sibling, &cur) };
+    }
+
+    if cur != stored_root {
+        return Err(op_error::LeaderKeyAccumulatorProofInvalid);
+    }
+
+    roots.remove(proof.root_index);
+    for (level, sibling) in proof.siblings.iter().enumerate() {
+        roots.push((level as u8, *sibling));
+    }
+
+    Ok(())
+}
+
+/// Records a newly-accepted leader key in its fork segment's accumulator. Runs alongside
+/// `BurnDB::insert_leader_key` wherever that's called -- a pruned node can then skip keeping
+/// the row `insert_leader_key` wrote once the key is later spent (see `consume_leader_key`),
+/// while a full node keeps both around and uses whichever lookup path the caller asks for.
+pub fn record_leader_key<'a>(tx: &mut DBTx<'a>, key: &LeaderKeyRegisterOp) -> Result<(), op_error> {
+    let mut roots = BurnDB::get_leader_key_accumulator_roots(tx, key.fork_segment_id)?;
+    insert_leaf(&mut roots, leaf_hash(key));
+    BurnDB::set_leader_key_accumulator_roots(tx, key.fork_segment_id, &roots)
+}
+
+/// Spends `key` against its fork segment's accumulator using `proof`, pruning its leaf on
+/// success so it can never be spent twice. This is the pruned-mode counterpart to
+/// `BurnDB::get_leader_key_at` + `is_leader_key_consumed`: it proves existence and unspent-ness
+/// in one step, from just the `O(log n)`-sized root set, with no full leader-key table needed.
+pub fn consume_leader_key<'a>(tx: &mut DBTx<'a>, key: &LeaderKeyRegisterOp, proof: &LeaderKeyAccumulatorProof) -> Result<(), op_error> {
+    let mut roots = BurnDB::get_leader_key_accumulator_roots(tx, key.fork_segment_id)?;
+    verify_and_prune(&mut roots, leaf_hash(key), proof)?;
+    BurnDB::set_leader_key_accumulator_roots(tx, key.fork_segment_id, &roots)
+}
+
+/// Rebuilds the accumulator forest from the full leader-key table and produces an inclusion
+/// proof for `target` -- the compatibility path for a node that keeps the full table. The
+/// replay inserts every key in table order with no pruning, so the proof's `root_index` and
+/// sibling count match a root set that has never had a key consumed; once `consume_leader_key`
+/// has pruned the stored roots, `verify_and_prune` rejects it.
+pub fn build_proof_from_table<'a>(tx: &mut DBTx<'a>, target: &LeaderKeyRegisterOp) -> Result<LeaderKeyAccumulatorProof, op_error> {
+    let all_keys = BurnDB::get_all_leader_keys(tx, target.fork_segment_id)?;
+    let target_leaf = leaf_hash(target);
+
+    // Unlike `insert_leaf`, which only needs to track each root's combined hash, this replay
+    // also needs each root's underlying leaves in order, so a proof can be read back off of
+    // whichever one ends up holding `target`.
+    let mut roots: Vec<(u8, Vec<[u8; 32]>)> = Vec::new();
+    for key in all_keys.iter() {
+        let mut leaves = vec![leaf_hash(key)];
+        let mut height = 0u8;
+
+        while roots.last().map(|(h, _)| *h) == Some(height) {
+            let (_, mut left_leaves) = roots.pop().unwrap();
+            left_leaves.extend(leaves);
+            leaves = left_leaves;
+            height += 1;
+        }
+
+        roots.push((height, leaves));
+    }
+
+    for (root_index, (height, leaves)) in roots.iter().enumerate() {
+        let leaf_index = match leaves.iter().position(|l| *l == target_leaf) {
+            Some(i) => i,
+            None => continue,
+        };
+
+        let mut level = leaves.clone();
+        let mut position = leaf_index;
+        let mut siblings = Vec::with_capacity(*height as usize);
+
+        for _ in 0..*height {
+            siblings.push(level[position ^ 1]);
+            position /= 2;
+            level = level.chunks(2).map(|pair| hash_pair(&pair[0], &pair[1])).collect();
+        }
+
+        return Ok(LeaderKeyAccumulatorProof {
+            root_index,
+            leaf_index: leaf_index as u64,
+            siblings,
+        });
+    }
+
+    Err(op_error::LeaderKeyAccumulatorProofInvalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(b: u8) -> [u8; 32] {
+        DoubleSha256::from_data(&[b]).0
+    }
+
+    #[test]
+    fn insert_leaf_merges_equal_height_roots_like_a_binary_counter() {
+        let mut roots: Vec<(u8, [u8; 32])> = Vec::new();
+
+        insert_leaf(&mut roots, leaf(1));
+        assert_eq!(roots.iter().map(|(h, _)| *h).collect::<Vec<u8>>(), vec![0]);
+
+        insert_leaf(&mut roots, leaf(2));
+        assert_eq!(roots.iter().map(|(h, _)| *h).collect::<Vec<u8>>(), vec![1]);
+
+        insert_leaf(&mut roots, leaf(3));
+        assert_eq!(roots.iter().map(|(h, _)| *h).collect::<Vec<u8>>(), vec![1, 0]);
+
+        insert_leaf(&mut roots, leaf(4));
+        assert_eq!(roots.iter().map(|(h, _)| *h).collect::<Vec<u8>>(), vec![2]);
+    }
+
+    #[test]
+    fn verify_and_prune_accepts_a_valid_proof_and_promotes_its_siblings() {
+        let mut roots: Vec<(u8, [u8; 32])> = Vec::new();
+        for i in 1..=4u8 {
+            insert_leaf(&mut roots, leaf(i));
+        }
+        // four leaves merge into a single height-2 root
+        assert_eq!(roots.len(), 1);
+
+        let l1 = leaf(1);
+        let l2 = leaf(2);
+        let l3 = leaf(3);
+        let l4 = leaf(4);
+        let n12 = hash_pair(&l1, &l2);
+        let n34 = hash_pair(&l3, &l4);
+
+        // prove and spend leaf 1 (index 0 under the single root)
+        let proof = LeaderKeyAccumulatorProof {
+            root_index: 0,
+            leaf_index: 0,
+            siblings: vec![l2, n34],
+        };
+
+        verify_and_prune(&mut roots, l1, &proof).unwrap();
+
+        // leaf 1's sibling (l2) and its uncle subtree (n34) are promoted to standalone roots
+        assert_eq!(roots, vec![(0u8, l2), (1u8, n34)]);
+    }
+
+    #[test]
+    fn verify_and_prune_rejects_a_proof_for_the_wrong_leaf() {
+        let mut roots: Vec<(u8, [u8; 32])> = Vec::new();
+        insert_leaf(&mut roots, leaf(1));
+        insert_leaf(&mut roots, leaf(2));
+
+        let proof = LeaderKeyAccumulatorProof {
+            root_index: 0,
+            leaf_index: 0,
+            siblings: vec![leaf(2)],
+        };
+
+        assert_eq!(verify_and_prune(&mut roots, leaf(99), &proof), Err(op_error::LeaderKeyAccumulatorProofInvalid));
+    }
+
+    #[test]
+    fn build_proof_from_table_reproduces_a_proof_verify_and_prune_accepts() {
+        let mut roots: Vec<(u8, [u8; 32])> = Vec::new();
+        let leaves: Vec<[u8; 32]> = (1..=5u8).map(leaf).collect();
+        for l in leaves.iter() {
+            insert_leaf(&mut roots, *l);
+        }
+
+        // Rebuild the same forest by replaying the raw leaves through the table-backed path's
+        // inner logic (mirrors what `build_proof_from_table` does against `BurnDB`, without a
+        // DB fixture): it should find leaf index 2 (the third leaf) and produce a proof that
+        // `verify_and_prune` accepts against the incrementally-built forest above.
+        let mut table_roots: Vec<(u8, Vec<[u8; 32]>)> = Vec::new();
+        for l in leaves.iter() {
+            let mut level = vec![*l];
+            let mut height = 0u8;
+            while table_roots.last().map(|(h, _)| *h) == Some(height) {
+                let (_, mut left) = table_roots.pop().unwrap();
+                left.extend(level);
+                level = left;
+                height += 1;
+            }
+            table_roots.push((height, level));
+        }
+
+        let target = leaves[2];
+        let mut found = None;
+        for (root_index, (height, level_leaves)) in table_roots.iter().enumerate() {
+            if let Some(leaf_index) = level_leaves.iter().position(|l| *l == target) {
+                let mut level = level_leaves.clone();
+                let mut position = leaf_index;
+                let mut siblings = Vec::with_capacity(*height as usize);
+                for _ in 0..*height {
+                    siblings.push(level[position ^ 1]);
+                    position /= 2;
+                    level = level.chunks(2).map(|pair| hash_pair(&pair[0], &pair[1])).collect();
+                }
+                found = Some(LeaderKeyAccumulatorProof { root_index, leaf_index: leaf_index as u64, siblings });
+                break;
+            }
+        }
+
+        let proof = found.expect("target leaf should be found in the rebuilt forest");
+        verify_and_prune(&mut roots, target, &proof).unwrap();
+    }
+}
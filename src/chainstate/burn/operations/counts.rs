@@ -0,0 +1,45 @@
+/*
+ copyright: (c) 2013-2018 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Cheap per-fork-segment operation tallies for status/RPC callers, e.g. "how many block
+//! commits landed in fork segment 0 between heights 100 and 200" -- the kind of question a
+//! node's status endpoint answers on every poll, and that shouldn't cost a full
+//! `LeaderBlockCommitOp`/`BurnchainSigner` deserialization (parsing out public keys and VRF
+//! seeds) per row just to add one to a counter. `BurnDB::block_commits_count` and
+//! `BurnDB::leader_keys_count` answer this by scanning row identifiers only; these wrappers
+//! just give that query path the same thin, op-module-facing shape as the rest of this
+//! directory (see `watch`) rather than requiring callers to reach into `BurnDB` directly.
+
+use chainstate::burn::operations::Error as op_error;
+
+use chainstate::burn::db::burndb::BurnDB;
+
+use util::db::DBTx;
+
+/// The number of `LeaderBlockCommitOp`s confirmed in `fork_segment_id` at a height in
+/// `[start_height, end_height]`.
+pub fn block_commits_count<'a>(tx: &mut DBTx<'a>, fork_segment_id: u64, start_height: u64, end_height: u64) -> Result<u64, op_error> {
+    BurnDB::block_commits_count(tx, fork_segment_id, start_height, end_height)
+}
+
+/// The number of `LeaderKeyRegisterOp`s confirmed in `fork_segment_id` at a height in
+/// `[start_height, end_height]`.
+pub fn leader_keys_count<'a>(tx: &mut DBTx<'a>, fork_segment_id: u64, start_height: u64, end_height: u64) -> Result<u64, op_error> {
+    BurnDB::leader_keys_count(tx, fork_segment_id, start_height, end_height)
+}
@@ -0,0 +1,171 @@
+/*
+ copyright: (c) 2013-2018 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Per-epoch consensus parameters, looked up by `LeaderBlockCommitOp::check` instead of being
+//! hard-coded in the checker itself. `StacksEpochId` names a configured rule era;
+//! `EpochList` holds the ordered set of `StacksEpoch`s a chain is configured with and resolves
+//! which one governs a given op's `epoch_num` via `active_at`.
+
+use std::ops::{Index, IndexMut};
+
+use address::AddressHashMode;
+
+/// A configured consensus-rule era. Distinct from `LeaderBlockCommitOp::epoch_num`, which is
+/// just a per-fork count of blocks since genesis -- `active_at` maps an `epoch_num` to the
+/// `StacksEpoch` (and hence `StacksEpochId`) whose rules govern it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StacksEpochId {
+    Epoch10,
+    Epoch20,
+    Epoch21,
+}
+
+/// The consensus parameters in force for one `StacksEpochId`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StacksEpoch {
+    pub epoch_id: StacksEpochId,
+    /// The `epoch_num` (blocks since genesis) at which this epoch's rules take over from
+    /// whichever one preceded it.
+    pub start_epoch_num: u64,
+    /// `hash_mode`s a block commit's `input`/a leader key's `address` may use under this epoch.
+    pub allowed_hash_modes: Vec<AddressHashMode>,
+    /// The minimum `burn_fee` a block commit must pay under this epoch, on top of (not instead
+    /// of) the "must be nonzero at all" check `check()` always runs.
+    pub min_burn_fee: u64,
+    /// Whether a block commit may start a new fork segment (build directly off of genesis)
+    /// under this epoch.
+    pub allows_new_fork_segments: bool,
+    /// The maximum byte length of a block commit's `memo` field under this epoch.
+    pub max_memo_len: usize,
+}
+
+impl StacksEpoch {
+    /// An epoch with no additional restrictions beyond what `check()` always enforces --
+    /// every `hash_mode` allowed, no minimum burn fee beyond nonzero, new fork segments
+    /// allowed, and a generous memo bound. Useful as a baseline for a chain that hasn't
+    /// configured anything epoch-specific yet.
+    pub fn permissive(epoch_id: StacksEpochId, start_epoch_num: u64) -> StacksEpoch {
+        StacksEpoch {
+            epoch_id,
+            start_epoch_num,
+            allowed_hash_modes: vec![
+                AddressHashMode::SerializeP2PKH,
+                AddressHashMode::SerializeP2WPKH,
+                AddressHashMode::SerializeP2WSH,
+            ],
+            min_burn_fee: 0,
+            allows_new_fork_segments: true,
+            max_memo_len: 80,
+        }
+    }
+}
+
+/// The ordered set of `StacksEpoch`s a chain is configured with, indexable by `StacksEpochId`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpochList(Vec<StacksEpoch>);
+
+impl EpochList {
+    pub fn new(epochs: Vec<StacksEpoch>) -> EpochList {
+        EpochList(epochs)
+    }
+
+    /// A single permissive `Epoch10` spanning every `epoch_num` from genesis -- the baseline
+    /// `EpochList` for a chain that hasn't configured anything epoch-specific.
+    pub fn permissive() -> EpochList {
+        EpochList::new(vec![StacksEpoch::permissive(StacksEpochId::Epoch10, 0)])
+    }
+
+    pub fn get(&self, id: StacksEpochId) -> Option<&StacksEpoch> {
+        self.0.iter().find(|e| e.epoch_id == id)
+    }
+
+    pub fn get_mut(&mut self, id: StacksEpochId) -> Option<&mut StacksEpoch> {
+        self.0.iter_mut().find(|e| e.epoch_id == id)
+    }
+
+    /// Resolves the `StacksEpoch` governing a given `epoch_num`: the configured epoch with the
+    /// latest `start_epoch_num` that is still `<= epoch_num`. `None` if `epoch_num` predates
+    /// every configured epoch (e.g. an empty `EpochList`).
+    pub fn active_at(&self, epoch_num: u64) -> Option<&StacksEpoch> {
+        self.0.iter()
+            .filter(|e| e.start_epoch_num <= epoch_num)
+            .max_by_key(|e| e.start_epoch_num)
+    }
+}
+
+impl Index<StacksEpochId> for EpochList {
+    type Output = StacksEpoch;
+
+    fn index(&self, id: StacksEpochId) -> &StacksEpoch {
+        self.get(id).unwrap_or_else(|| panic!("EpochList has no configured {:?} epoch", id))
+    }
+}
+
+impl IndexMut<StacksEpochId> for EpochList {
+    fn index_mut(&mut self, id: StacksEpochId) -> &mut StacksEpoch {
+        self.get_mut(id).unwrap_or_else(|| panic!("EpochList has no configured {:?} epoch", id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> EpochList {
+        EpochList::new(vec![
+            StacksEpoch::permissive(StacksEpochId::Epoch10, 0),
+            StacksEpoch { min_burn_fee: 500, ..StacksEpoch::permissive(StacksEpochId::Epoch20, 100) },
+            StacksEpoch { min_burn_fee: 1000, ..StacksEpoch::permissive(StacksEpochId::Epoch21, 200) },
+        ])
+    }
+
+    #[test]
+    fn indexes_by_epoch_id() {
+        let epochs = sample();
+        assert_eq!(epochs[StacksEpochId::Epoch20].min_burn_fee, 500);
+        assert_eq!(epochs.get(StacksEpochId::Epoch21).unwrap().min_burn_fee, 1000);
+        assert!(epochs.get(StacksEpochId::Epoch10).is_some());
+    }
+
+    #[test]
+    fn index_mut_allows_in_place_updates() {
+        let mut epochs = sample();
+        epochs[StacksEpochId::Epoch10].min_burn_fee = 42;
+        assert_eq!(epochs[StacksEpochId::Epoch10].min_burn_fee, 42);
+    }
+
+    #[test]
+    fn active_at_resolves_the_latest_epoch_whose_start_is_not_after_epoch_num() {
+        let epochs = sample();
+        assert_eq!(epochs.active_at(0).unwrap().epoch_id, StacksEpochId::Epoch10);
+        assert_eq!(epochs.active_at(99).unwrap().epoch_id, StacksEpochId::Epoch10);
+        assert_eq!(epochs.active_at(100).unwrap().epoch_id, StacksEpochId::Epoch20);
+        assert_eq!(epochs.active_at(199).unwrap().epoch_id, StacksEpochId::Epoch20);
+        assert_eq!(epochs.active_at(200).unwrap().epoch_id, StacksEpochId::Epoch21);
+        assert_eq!(epochs.active_at(1_000_000).unwrap().epoch_id, StacksEpochId::Epoch21);
+    }
+
+    #[test]
+    fn active_at_returns_none_before_any_configured_epoch() {
+        let epochs = EpochList::new(vec![StacksEpoch::permissive(StacksEpochId::Epoch20, 100)]);
+        assert!(epochs.active_at(0).is_none());
+        assert!(epochs.active_at(99).is_none());
+        assert!(epochs.active_at(100).is_some());
+    }
+}
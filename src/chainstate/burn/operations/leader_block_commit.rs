@@ -21,7 +21,7 @@ use address::AddressHashMode;
 use chainstate::burn::ConsensusHash;
 use chainstate::burn::operations::Error as op_error;
 use chainstate::burn::Opcodes;
-use chainstate::burn::{BlockHeaderHash, VRFSeed};
+use chainstate::burn::{BlockHeaderHash, VRFSeed, BlockSnapshot};
 
 use chainstate::burn::db::burndb::BurnDB;
 
@@ -35,6 +35,11 @@ use chainstate::burn::operations::{
     parse_u16_from_be
 };
 
+use chainstate::burn::operations::accumulator;
+use chainstate::burn::operations::accumulator::LeaderKeyAccumulatorProof;
+use chainstate::burn::operations::epoch::EpochList;
+
+use chainstate::stacks::StacksAddress;
 use chainstate::stacks::StacksPublicKey;
 use chainstate::stacks::StacksPrivateKey;
 
@@ -51,12 +56,118 @@ use burnchains::{
 
 use util::log;
 use util::hash::to_hex;
+use util::hash::hex_bytes;
+use util::hash::DoubleSha256;
 use util::vrf::VRF;
 use util::vrf::VRFPublicKey;
 use util::vrf::VRFPrivateKey;
 use util::db::DBConn;
 use util::db::DBTx;
 
+use chainstate::burn::operations::serialization::{Serializable, Deserializable};
+
+/// The payload length, after the memo byte, at which a commit's trailing bytes are read as a
+/// PoW nonce instead of ending at the memo: a classic PoB commit's payload is exactly
+/// `77..POW_PAYLOAD_LEN` bytes long (just the memo byte), while a PoW commit's is at least
+/// `POW_PAYLOAD_LEN`, with the 4 bytes at `76..80` read as a big-endian nonce in place of the
+/// memo byte. This lets a node distinguish the two forms without a dedicated version byte, so
+/// the PoW format can be soft-forked in.
+const POW_PAYLOAD_LEN: usize = 80;
+
+/// How many of the most recent sortitions' snapshots a retarget looks back over.
+const POW_RETARGET_WINDOW: u64 = 10;
+
+/// How far a retarget may scale the genesis target -- this keeps a single unusually busy
+/// window from tightening the target by more than this factor.
+const POW_MAX_RETARGET_FACTOR: u64 = 4;
+
+/// The average per-block burn amount a retarget window is calibrated against: a window that
+/// burned more than this, on average, tightens the PoW target by the same proportion (more
+/// burn competition implies more hashing competition is expected to follow), clamped to
+/// `POW_MAX_RETARGET_FACTOR`. A quieter window leaves the target at `POW_GENESIS_TARGET`:
+/// that target is already the all-ones maximum, so scaling it up saturates.
+const POW_REFERENCE_BURN_PER_BLOCK: u64 = 10_000;
+
+/// The PoW target used once `check()` can't find any sortition history to retarget from, e.g.
+/// in the handful of blocks right after this chain's genesis: deliberately easy, so the first
+/// PoW commits don't need to find a needle-in-a-haystack nonce before there's a real target to
+/// retarget against.
+const POW_GENESIS_TARGET: [u8; 32] = [0xff; 32];
+
+/// Multiplies a big-endian 256-bit target by a small integer factor, saturating at the
+/// maximum target (all bits set) instead of overflowing.
+fn multiply_target(target: &[u8; 32], factor: u64) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    let mut carry: u128 = 0;
+    for i in (0..32).rev() {
+        let product = (target[i] as u128) * (factor as u128) + carry;
+        out[i] = (product & 0xff) as u8;
+        carry = product >> 8;
+    }
+    if carry > 0 {
+        return [0xff; 32];
+    }
+    out
+}
+
+/// Divides a big-endian 256-bit target by a small integer divisor, discarding the remainder.
+fn divide_target(target: &[u8; 32], divisor: u64) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    let mut remainder: u128 = 0;
+    for i in 0..32 {
+        let acc = (remainder << 8) | (target[i] as u128);
+        out[i] = (acc / (divisor as u128)) as u8;
+        remainder = acc % (divisor as u128);
+    }
+    out
+}
+
+/// Derives the PoW target that a PoW commit building on `chain_tip` must beat, from the burn
+/// amounts of the last `POW_RETARGET_WINDOW` sortition snapshots -- never from a value
+/// committed in the Bitcoin tx itself, so this can't be gamed by a miner claiming a
+/// convenient difficulty, and so the format can be soft-forked in without a consensus-breaking
+/// change to how existing (non-PoW) commits are read. Any verifier with the same burn
+/// database derives the same target independently.
+fn retarget_pow_target<'a>(tx: &mut DBTx<'a>, chain_tip: &BlockSnapshot) -> [u8; 32] {
+    let mut total_burn: u128 = 0;
+    let mut windowed: u64 = 0;
+    let mut cursor_hash = chain_tip.burn_header_hash.clone();
+
+    while windowed < POW_RETARGET_WINDOW {
+        let snapshot = match BurnDB::get_block_snapshot(tx, &cursor_hash)
+            .expect("FATAL: failed to query block snapshot")
+        {
+            Some(s) => s,
+            None => break,
+        };
+
+        total_burn += snapshot.total_burn as u128;
+        windowed += 1;
+
+        if snapshot.burn_header_hash == snapshot.parent_burn_header_hash {
+            // reached the genesis snapshot, which is its own parent
+            break;
+        }
+        cursor_hash = snapshot.parent_burn_header_hash.clone();
+    }
+
+    if windowed == 0 {
+        return POW_GENESIS_TARGET;
+    }
+
+    let avg_burn = (total_burn / (windowed as u128)) as u64;
+
+    if avg_burn > POW_REFERENCE_BURN_PER_BLOCK {
+        let factor = (avg_burn / POW_REFERENCE_BURN_PER_BLOCK).min(POW_MAX_RETARGET_FACTOR).max(1);
+        divide_target(&POW_GENESIS_TARGET, factor)
+    } else if avg_burn > 0 {
+        let factor = (POW_REFERENCE_BURN_PER_BLOCK / avg_burn).min(POW_MAX_RETARGET_FACTOR).max(1);
+        multiply_target(&POW_GENESIS_TARGET, factor)
+    } else {
+        multiply_target(&POW_GENESIS_TARGET, POW_MAX_RETARGET_FACTOR)
+    }
+}
+
 // return type from parse_data below
 struct ParsedData {
     block_header_hash: BlockHeaderHash,
@@ -66,7 +177,36 @@ struct ParsedData {
     key_block_backptr: u16,
     key_vtxindex: u16,
     epoch_num: u32,
-    memo: Vec<u8>
+    memo: Vec<u8>,
+    pow_nonce: Option<u32>
+}
+
+impl Serializable for ParsedData {
+    // Wraps the same `block hash || new seed || parent delta/txoff || key delta/txoff ||
+    // epoch || memo [|| PoW nonce]` layout that `parse_data` reads directly off the
+    // OP_RETURN, for contexts (e.g. a relay mempool or tooling) that need the payload to
+    // describe its own version.
+    fn serialize(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(77 + self.memo.len() + 4);
+        bytes.extend_from_slice(self.block_header_hash.as_bytes());
+        bytes.extend_from_slice(self.new_seed.as_bytes());
+        bytes.extend_from_slice(&self.parent_block_backptr.to_be_bytes());
+        bytes.extend_from_slice(&self.parent_vtxindex.to_be_bytes());
+        bytes.extend_from_slice(&self.key_block_backptr.to_be_bytes());
+        bytes.extend_from_slice(&self.key_vtxindex.to_be_bytes());
+        bytes.extend_from_slice(&self.epoch_num.to_be_bytes());
+        bytes.extend_from_slice(&self.memo);
+        if let Some(nonce) = self.pow_nonce {
+            bytes.extend_from_slice(&nonce.to_be_bytes());
+        }
+        bytes
+    }
+}
+
+impl Deserializable for ParsedData {
+    fn deserialize(_version: u8, data: &[u8]) -> Result<ParsedData, op_error> {
+        LeaderBlockCommitOp::parse_data(&data.to_vec()).ok_or(op_error::ParseError)
+    }
 }
 
 impl LeaderBlockCommitOp {
@@ -92,7 +232,10 @@ impl LeaderBlockCommitOp {
             block_height: 0,
             burn_header_hash: BurnchainHeaderHash([0u8; 32]),
 
-            fork_segment_id: 0
+            fork_segment_id: 0,
+            pow_nonce: None,
+            treasury_fee: 0,
+            treasury_address: None,
         }
     }
 
@@ -119,6 +262,9 @@ impl LeaderBlockCommitOp {
             burn_header_hash: BurnchainHeaderHash([0u8; 32]),
 
             fork_segment_id: 0,
+            pow_nonce: None,
+            treasury_fee: 0,
+            treasury_address: None,
         }
     }
 
@@ -175,35 +321,151 @@ impl LeaderBlockCommitOp {
         self.fork_segment_id = block_header.fork_segment_id;
     }
 
-    fn parse_data(data: &Vec<u8>) -> Option<ParsedData> {
-        /*
-            TODO: pick one of these.
+    /// Builds this op's canonical OP_RETURN payload bytes -- the same bytes `to_ledger_csv_row`
+    /// hex-encodes and a commit transaction ultimately carries. Exposed so a signer (in-process
+    /// or an external device via `burnchains::signing::BurnSigningDevice`) always signs exactly
+    /// what ends up on the burnchain.
+    pub fn preimage(&self) -> Vec<u8> {
+        let payload = ParsedData {
+            block_header_hash: self.block_header_hash.clone(),
+            new_seed: self.new_seed.clone(),
+            parent_block_backptr: self.parent_block_backptr,
+            parent_vtxindex: self.parent_vtxindex,
+            key_block_backptr: self.key_block_backptr,
+            key_vtxindex: self.key_vtxindex,
+            epoch_num: self.epoch_num,
+            memo: self.memo.clone(),
+            pow_nonce: self.pow_nonce,
+        };
 
-            TODO: we probably don't need to commit to the PoW difficulty on-chain if all we're doing is training miners.
-            we can add it as something committed to in the MARF, so we can probably soft-fork it in if needed
-            (assuming we want to make the transition to native PoW at all).
+        payload.serialize()
+    }
 
-            Hybrid PoB/PoW Wire format:
-            0      2  3               34               67     68     70    71   72     76    80
-            |------|--|----------------|---------------|------|------|-----|-----|-----|-----|
-             magic  op   block hash       new seed     parent parent key   key   epoch  PoW
-                       (31-byte; lead 0)               delta  txoff  delta txoff num.   nonce
+    /// Serializes this op as one row of the burn-op ledger CSV (see
+    /// `chainstate::burn::operations::ledger_csv`): the envelope fields plus this op's
+    /// OP_RETURN payload as `preimage()` builds it, hex-encoded without the version byte
+    /// `serialization::to_versioned_bytes` would prepend. The signer is
+    /// recorded as `hash_mode|num_sigs|pubkey_hex;pubkey_hex;...`; `from_ledger_csv_row` only
+    /// recognizes `AddressHashMode` variants it's seen before (currently just
+    /// `SerializeP2PKH`), since that enum isn't visible in full from here.
+    pub fn to_ledger_csv_row(&self) -> String {
+        let pubkeys_hex = self.input.public_keys.iter()
+            .map(|pk| pk.to_hex())
+            .collect::<Vec<String>>()
+            .join(";");
+        let signer = format!("{:?}|{}|{}", self.input.hash_mode, self.input.num_sigs, pubkeys_hex);
+        let treasury_address_hex = match self.treasury_address {
+            Some(ref addr) => to_hex(&addr.to_bytes()),
+            None => "".to_string(),
+        };
 
-             Note that `data` is missing the first 3 bytes -- the magic and op have been stripped
+        format!(
+            "leader_block_commit,{},{},{},{},{},{},{},{},{},{}",
+            to_hex(self.txid.as_bytes()),
+            self.vtxindex,
+            self.block_height,
+            to_hex(self.burn_header_hash.as_bytes()),
+            self.fork_segment_id,
+            self.burn_fee,
+            signer,
+            to_hex(&self.preimage()),
+            self.treasury_fee,
+            treasury_address_hex,
+        )
+    }
 
-             The values parent-txoff and key-txoff are in network byte order.
+    /// Reconstructs a `LeaderBlockCommitOp` from one row written by `to_ledger_csv_row`.
+    pub fn from_ledger_csv_row(row: &str) -> Result<LeaderBlockCommitOp, op_error> {
+        let fields: Vec<&str> = row.split(',').collect();
+        if fields.len() != 11 || fields[0] != "leader_block_commit" {
+            return Err(op_error::ParseError);
+        }
+
+        let txid = Txid::from_hex(fields[1]).map_err(|_| op_error::ParseError)?;
+        let vtxindex: u32 = fields[2].parse().map_err(|_| op_error::ParseError)?;
+        let block_height: u64 = fields[3].parse().map_err(|_| op_error::ParseError)?;
+        let burn_header_hash = BurnchainHeaderHash::from_hex(fields[4]).map_err(|_| op_error::ParseError)?;
+        let fork_segment_id: u64 = fields[5].parse().map_err(|_| op_error::ParseError)?;
+        let burn_fee: u64 = fields[6].parse().map_err(|_| op_error::ParseError)?;
+
+        let signer_fields: Vec<&str> = fields[7].splitn(3, '|').collect();
+        if signer_fields.len() != 3 {
+            return Err(op_error::ParseError);
+        }
+        let hash_mode = match signer_fields[0] {
+            "SerializeP2PKH" => AddressHashMode::SerializeP2PKH,
+            _ => return Err(op_error::ParseError),
+        };
+        let num_sigs: usize = signer_fields[1].parse().map_err(|_| op_error::ParseError)?;
+        let public_keys = if signer_fields[2].is_empty() {
+            vec![]
+        } else {
+            signer_fields[2].split(';')
+                .map(|hex| StacksPublicKey::from_hex(hex).map_err(|_| op_error::ParseError))
+                .collect::<Result<Vec<StacksPublicKey>, op_error>>()?
+        };
+        let input = BurnchainSigner { hash_mode, num_sigs, public_keys };
+
+        let payload_bytes = hex_bytes(fields[8]).map_err(|_| op_error::ParseError)?;
+        let payload = LeaderBlockCommitOp::parse_data(&payload_bytes).ok_or(op_error::ParseError)?;
 
+        let treasury_fee: u64 = fields[9].parse().map_err(|_| op_error::ParseError)?;
+        let treasury_address = if fields[10].is_empty() {
+            None
+        } else {
+            let addr_bytes = hex_bytes(fields[10]).map_err(|_| op_error::ParseError)?;
+            Some(StacksAddress::from_bytes(&addr_bytes).map_err(|_| op_error::ParseError)?)
+        };
+
+        Ok(LeaderBlockCommitOp {
+            block_header_hash: payload.block_header_hash,
+            new_seed: payload.new_seed,
+            parent_block_backptr: payload.parent_block_backptr,
+            parent_vtxindex: payload.parent_vtxindex,
+            key_block_backptr: payload.key_block_backptr,
+            key_vtxindex: payload.key_vtxindex,
+            epoch_num: payload.epoch_num,
+            memo: payload.memo,
+            burn_fee,
+            input,
+            txid,
+            vtxindex,
+            block_height,
+            burn_header_hash,
+            fork_segment_id,
+            pow_nonce: payload.pow_nonce,
+            treasury_fee,
+            treasury_address,
+        })
+    }
+
+    fn parse_data(data: &Vec<u8>) -> Option<ParsedData> {
+        /*
             Wire format:
-            0      2  3            35               67     69     71    73   75     79    80
-            |------|--|-------------|---------------|------|------|-----|-----|-----|-----|
-             magic  op   block hash     new seed     parent parent key   key   epoch  memo
-                                                     delta  txoff  delta txoff num.
+            0      2  3            35               67     69     71    73   75     76    80
+            |------|--|-------------|---------------|------|------|-----|-----|-----|--...--|
+             magic  op   block hash     new seed     parent parent key   key   epoch  memo/
+                                                     delta  txoff  delta txoff num.   PoW nonce
 
              Note that `data` is missing the first 3 bytes -- the magic and op have been stripped
 
              The values parent-delta, parent-txoff, key-delta, and key-txoff are in network byte order.
 
              parent-delta and parent-txoff will both be 0 if this block builds off of the genesis block.
+
+             The last field is read one of two ways, depending on how much of the payload is
+             present -- this is the "flag" that picks the commit's mode, in place of a
+             dedicated version byte:
+
+               - PoB commit (payload is 77..POW_PAYLOAD_LEN bytes): byte 76 is the memo, same
+                 as before this format grew a PoW mode.
+
+               - PoW commit (payload is at least POW_PAYLOAD_LEN bytes): bytes 76..80 hold a
+                 4-byte, big-endian PoW nonce in place of the memo. `check()` recomputes the
+                 PoW hash from this nonce and the rest of the commit's fields, and rejects the
+                 commit if it doesn't beat the retargeted difficulty. The difficulty itself is
+                 never committed to on-chain (see `check()`), so this format can be soft-forked
+                 in without changing how PoB commits are read.
         */
 
         if data.len() < 77 {
@@ -219,7 +481,12 @@ impl LeaderBlockCommitOp {
         let key_block_backptr = parse_u16_from_be(&data[68..70]).unwrap();
         let key_vtxindex = parse_u16_from_be(&data[70..72]).unwrap();
         let epoch_num = parse_u32_from_be(&data[72..76]).unwrap();
-        let memo = data[76..77].to_vec();
+
+        let (memo, pow_nonce) = if data.len() >= POW_PAYLOAD_LEN {
+            (vec![], Some(parse_u32_from_be(&data[76..80]).unwrap()))
+        } else {
+            (data[76..77].to_vec(), None)
+        };
 
         Some(ParsedData {
             block_header_hash,
@@ -229,7 +496,8 @@ impl LeaderBlockCommitOp {
             key_block_backptr,
             key_vtxindex,
             epoch_num,
-            memo
+            memo,
+            pow_nonce,
         })
     }
 
@@ -305,6 +573,15 @@ impl LeaderBlockCommitOp {
             return Err(op_error::ParseError);
         }
 
+        // outputs[1], if present, is the treasury burn split (see `check_impl`); a commit with
+        // no such output simply has a treasury_fee of 0, which only matters once the treasury
+        // rule activates.
+        let (treasury_fee, treasury_address) = if outputs.len() > 1 {
+            (outputs[1].amount, Some(outputs[1].address.clone()))
+        } else {
+            (0, None)
+        };
+
         Ok(LeaderBlockCommitOp {
             block_header_hash: data.block_header_hash,
             new_seed: data.new_seed,
@@ -323,7 +600,10 @@ impl LeaderBlockCommitOp {
             block_height: block_height,
             burn_header_hash: block_hash.clone(),
 
-            fork_segment_id: fork_segment_id
+            fork_segment_id: fork_segment_id,
+            pow_nonce: data.pow_nonce,
+            treasury_fee,
+            treasury_address,
         })
     }
 }
@@ -333,7 +613,23 @@ impl BlockstackOperation for LeaderBlockCommitOp {
         LeaderBlockCommitOp::parse_from_tx(block_header.block_height, block_header.fork_segment_id, &block_header.block_hash, tx)
     }
         
-    fn check<'a>(&self, burnchain: &Burnchain, block_header: &BurnchainBlockHeader, tx: &mut DBTx<'a>) -> Result<(), op_error> {
+    fn check<'a>(&self, burnchain: &Burnchain, block_header: &BurnchainBlockHeader, epochs: &EpochList, tx: &mut DBTx<'a>) -> Result<(), op_error> {
+        self.check_impl(burnchain, block_header, epochs, tx, None)
+    }
+}
+
+impl LeaderBlockCommitOp {
+    /// Pruned-mode counterpart to `check()`: every other validation this op requires (burn
+    /// amount, epoch, parent back-pointer, input address, PoW) is identical, but rather than
+    /// looking up the referenced leader key in the full `BurnDB` leader-key table, the caller
+    /// supplies the key it claims to reference plus an accumulator inclusion proof (see
+    /// `chainstate::burn::operations::accumulator`) -- letting a pruned node validate a block
+    /// commit without keeping every leader key ever registered around.
+    pub fn check_with_accumulator_proof<'a>(&self, burnchain: &Burnchain, block_header: &BurnchainBlockHeader, epochs: &EpochList, tx: &mut DBTx<'a>, register_key: &LeaderKeyRegisterOp, key_proof: &LeaderKeyAccumulatorProof) -> Result<(), op_error> {
+        self.check_impl(burnchain, block_header, epochs, tx, Some((register_key, key_proof)))
+    }
+
+    fn check_impl<'a>(&self, burnchain: &Burnchain, block_header: &BurnchainBlockHeader, epochs: &EpochList, tx: &mut DBTx<'a>, pruned_proof: Option<(&LeaderKeyRegisterOp, &LeaderKeyAccumulatorProof)>) -> Result<(), op_error> {
         let leader_key_block_height = self.block_height - (self.key_block_backptr as u64);
         let parent_block_height = self.block_height - (self.parent_block_backptr as u64);
 
@@ -344,13 +640,39 @@ impl BlockstackOperation for LeaderBlockCommitOp {
             warn!("Invalid block commit: no burn amount");
             return Err(op_error::BlockCommitBadInput);
         }
-        
+
+        /////////////////////////////////////////////////////////////////////////////////////
+        // Once the treasury rule activates, a configured fraction of burn_fee must also be
+        // burned to the configured treasury address -- a deployment with no treasury address
+        // configured never enforces this, so existing chain history stays valid.
+        /////////////////////////////////////////////////////////////////////////////////////
+        if let Some(ref treasury_address) = burnchain.treasury_address {
+            if self.block_height >= burnchain.treasury_activation_height {
+                if burnchain.treasury_denominator == 0 {
+                    warn!("Invalid burnchain config: treasury_address is set but treasury_denominator is 0");
+                    return Err(op_error::InvalidInput);
+                }
+
+                let required_fee = ((self.burn_fee as u128) * (burnchain.treasury_numerator as u128) / (burnchain.treasury_denominator as u128)) as u64;
+                let paid_treasury = self.treasury_address.as_ref() == Some(treasury_address);
+
+                if self.treasury_fee < required_fee || !paid_treasury {
+                    warn!("Invalid block commit: treasury burn of {} to {:?} does not meet the required {} to {:?}",
+                          self.treasury_fee, self.treasury_address, required_fee, treasury_address);
+                    return Err(op_error::BlockCommitInsufficientTreasuryBurn);
+                }
+            }
+        }
+
         /////////////////////////////////////////////////////////////////////////////////////
         // This tx's epoch number must match the current epoch
         /////////////////////////////////////////////////////////////////////////////////////
     
+        // In a fully-synced node this row always exists, but a regtest/devnet chain that gets
+        // reset and rewound can legitimately be missing it -- that's recoverable (re-anchor and
+        // resync), not a reason to panic.
         let first_block_snapshot = BurnDB::get_first_block_snapshot(tx)
-            .expect("FATAL: failed to query first block snapshot");
+            .map_err(|_| op_error::MissingHeaders)?;
 
         if self.block_height < first_block_snapshot.block_height {
             warn!("Invalid block commit: predates genesis height {}", first_block_snapshot.block_height);
@@ -362,7 +684,38 @@ impl BlockstackOperation for LeaderBlockCommitOp {
             warn!("Invalid block commit: current epoch is {}; got {}", target_epoch, self.epoch_num);
             return Err(op_error::BlockCommitBadEpoch);
         }
-        
+
+        /////////////////////////////////////////////////////////////////////////////////////
+        // This epoch's consensus rules -- which hash modes it accepts, its minimum burn fee,
+        // whether it allows starting a new fork segment, and its memo-byte bound -- are looked
+        // up by epoch rather than hard-coded, so a new epoch's rules are a new `StacksEpoch` in
+        // the node's configured `EpochList`, not a code change here.
+        /////////////////////////////////////////////////////////////////////////////////////
+
+        let epoch_rules = epochs.active_at(self.epoch_num as u64)
+            .ok_or(op_error::BlockCommitBadEpoch)?;
+
+        if !epoch_rules.allowed_hash_modes.contains(&self.input.hash_mode) {
+            warn!("Invalid block commit: hash mode {:?} is not allowed under epoch {:?}", self.input.hash_mode, epoch_rules.epoch_id);
+            return Err(op_error::BlockCommitBadInput);
+        }
+
+        if self.burn_fee < epoch_rules.min_burn_fee {
+            warn!("Invalid block commit: burn fee {} is below the {} minimum for epoch {:?}", self.burn_fee, epoch_rules.min_burn_fee, epoch_rules.epoch_id);
+            return Err(op_error::BlockCommitBadInput);
+        }
+
+        if self.memo.len() > epoch_rules.max_memo_len {
+            warn!("Invalid block commit: memo of {} bytes exceeds the {}-byte bound for epoch {:?}", self.memo.len(), epoch_rules.max_memo_len, epoch_rules.epoch_id);
+            return Err(op_error::BlockCommitBadInput);
+        }
+
+        let starts_new_fork_segment = self.parent_block_backptr == 0 && self.parent_vtxindex == 0;
+        if starts_new_fork_segment && !epoch_rules.allows_new_fork_segments {
+            warn!("Invalid block commit: epoch {:?} does not allow starting a new fork segment", epoch_rules.epoch_id);
+            return Err(op_error::BlockCommitNoParent);
+        }
+
         /////////////////////////////////////////////////////////////////////////////////////
         // There must exist a previously-accepted *unused* key from a LeaderKeyRegister
         /////////////////////////////////////////////////////////////////////////////////////
@@ -377,23 +730,42 @@ impl BlockstackOperation for LeaderBlockCommitOp {
             .expect("FATAL: failed to query parent block snapshot")
             .expect("FATAL: no parent snapshot in the DB");
 
-        let register_key_opt = BurnDB::get_leader_key_at(tx, leader_key_block_height, self.key_vtxindex.into(), chain_tip.fork_segment_id)
-            .expect("Sqlite failure while getting a prior leader VRF key");
+        let register_key = match pruned_proof {
+            None => {
+                let register_key_opt = BurnDB::get_leader_key_at(tx, leader_key_block_height, self.key_vtxindex.into(), chain_tip.fork_segment_id)
+                    .expect("Sqlite failure while getting a prior leader VRF key");
 
-        if register_key_opt.is_none() {
-            warn!("Invalid block commit: no corresponding leader key at {},{} in fork {}", leader_key_block_height, self.key_vtxindex, chain_tip.fork_segment_id);
-            return Err(op_error::BlockCommitNoLeaderKey);
-        }
+                if register_key_opt.is_none() {
+                    warn!("Invalid block commit: no corresponding leader key at {},{} in fork {}", leader_key_block_height, self.key_vtxindex, chain_tip.fork_segment_id);
+                    return Err(op_error::BlockCommitNoLeaderKey);
+                }
 
-        let register_key = register_key_opt.unwrap();
-    
-        let is_key_consumed = BurnDB::is_leader_key_consumed(tx, chain_tip.block_height, &register_key, chain_tip.fork_segment_id)
-            .expect("Sqlite failure while verifying that a leader VRF key is not consumed");
+                let register_key = register_key_opt.unwrap();
 
-        if is_key_consumed {
-            warn!("Invalid block commit: leader key at ({},{}) is already used as of {} in fork {}", register_key.block_height, register_key.vtxindex, chain_tip.block_height, chain_tip.fork_segment_id);
-            return Err(op_error::BlockCommitLeaderKeyAlreadyUsed);
-        }
+                let is_key_consumed = BurnDB::is_leader_key_consumed(tx, chain_tip.block_height, &register_key, chain_tip.fork_segment_id)
+                    .expect("Sqlite failure while verifying that a leader VRF key is not consumed");
+
+                if is_key_consumed {
+                    warn!("Invalid block commit: leader key at ({},{}) is already used as of {} in fork {}", register_key.block_height, register_key.vtxindex, chain_tip.block_height, chain_tip.fork_segment_id);
+                    return Err(op_error::BlockCommitLeaderKeyAlreadyUsed);
+                }
+
+                register_key
+            },
+            Some((candidate_key, key_proof)) => {
+                if candidate_key.block_height != leader_key_block_height || candidate_key.vtxindex != self.key_vtxindex.into() {
+                    warn!("Invalid block commit: supplied leader key is at ({},{}), not the referenced ({},{})", candidate_key.block_height, candidate_key.vtxindex, leader_key_block_height, self.key_vtxindex);
+                    return Err(op_error::BlockCommitNoLeaderKey);
+                }
+
+                if accumulator::consume_leader_key(tx, candidate_key, key_proof).is_err() {
+                    warn!("Invalid block commit: leader key at ({},{}) failed accumulator proof or is already used in fork {}", candidate_key.block_height, candidate_key.vtxindex, chain_tip.fork_segment_id);
+                    return Err(op_error::BlockCommitLeaderKeyAlreadyUsed);
+                }
+
+                candidate_key.clone()
+            },
+        };
 
         /////////////////////////////////////////////////////////////////////////////////////
         // There must exist a previously-accepted block from a LeaderBlockCommit, or this
@@ -439,6 +811,32 @@ impl BlockstackOperation for LeaderBlockCommitOp {
             return Err(op_error::BlockCommitBadInput);
         }
 
+        /////////////////////////////////////////////////////////////////////////////////////
+        // If this is a PoW commit, its nonce must beat the target derived from recent
+        // sortition history (never a difficulty claimed in the tx itself -- see
+        // `retarget_pow_target`).
+        /////////////////////////////////////////////////////////////////////////////////////
+
+        if let Some(nonce) = self.pow_nonce {
+            let mut body = Vec::with_capacity(80);
+            body.extend_from_slice(self.block_header_hash.as_bytes());
+            body.extend_from_slice(self.new_seed.as_bytes());
+            body.extend_from_slice(&self.parent_block_backptr.to_be_bytes());
+            body.extend_from_slice(&self.parent_vtxindex.to_be_bytes());
+            body.extend_from_slice(&self.key_block_backptr.to_be_bytes());
+            body.extend_from_slice(&self.key_vtxindex.to_be_bytes());
+            body.extend_from_slice(&self.epoch_num.to_be_bytes());
+            body.extend_from_slice(&nonce.to_be_bytes());
+
+            let digest = DoubleSha256::from_data(&body).0;
+            let target = retarget_pow_target(tx, &chain_tip);
+
+            if digest >= target {
+                warn!("Invalid block commit: PoW digest {} does not beat target {}", to_hex(&digest), to_hex(&target));
+                return Err(op_error::BlockCommitBadPoW);
+            }
+        }
+
         Ok(())
     }
 }
@@ -470,16 +868,16 @@ mod tests {
         BlockstackOperationType
     };
 
+    use chainstate::burn::operations::serialization::{to_versioned_bytes, from_versioned_bytes};
+
     use util::vrf::VRFPublicKey;
     use util::hash::hex_bytes;
     use util::log;
     
-    use chainstate::stacks::StacksAddress;
     use chainstate::stacks::StacksPublicKey;
 
     use chainstate::burn::OpsHash;
     use chainstate::burn::SortitionHash;
-    use chainstate::burn::BlockSnapshot;
 
     struct OpFixture {
         txstr: String,
@@ -499,6 +897,87 @@ mod tests {
         Ok(tx)
     }
 
+    #[test]
+    fn parsed_data_roundtrips_through_the_versioned_wire_format() {
+        let parsed = ParsedData {
+            block_header_hash: BlockHeaderHash::from_bytes(&hex_bytes("2222222222222222222222222222222222222222222222222222222222222222").unwrap()).unwrap(),
+            new_seed: VRFSeed::from_bytes(&hex_bytes("3333333333333333333333333333333333333333333333333333333333333333").unwrap()).unwrap(),
+            parent_block_backptr: 0x4140,
+            parent_vtxindex: 0x4342,
+            key_block_backptr: 0x5150,
+            key_vtxindex: 0x6160,
+            epoch_num: 0x71706362,
+            memo: vec![0x80],
+            pow_nonce: None,
+        };
+
+        let encoded = to_versioned_bytes(&parsed);
+        let decoded: ParsedData = from_versioned_bytes(&encoded).unwrap();
+
+        assert_eq!(decoded.block_header_hash, parsed.block_header_hash);
+        assert_eq!(decoded.new_seed, parsed.new_seed);
+        assert_eq!(decoded.parent_block_backptr, parsed.parent_block_backptr);
+        assert_eq!(decoded.parent_vtxindex, parsed.parent_vtxindex);
+        assert_eq!(decoded.key_block_backptr, parsed.key_block_backptr);
+        assert_eq!(decoded.key_vtxindex, parsed.key_vtxindex);
+        assert_eq!(decoded.epoch_num, parsed.epoch_num);
+        assert_eq!(decoded.memo, parsed.memo);
+        assert_eq!(decoded.pow_nonce, parsed.pow_nonce);
+    }
+
+    #[test]
+    fn parsed_data_roundtrips_a_pow_nonce_through_the_versioned_wire_format() {
+        let parsed = ParsedData {
+            block_header_hash: BlockHeaderHash::from_bytes(&hex_bytes("2222222222222222222222222222222222222222222222222222222222222222").unwrap()).unwrap(),
+            new_seed: VRFSeed::from_bytes(&hex_bytes("3333333333333333333333333333333333333333333333333333333333333333").unwrap()).unwrap(),
+            parent_block_backptr: 0x4140,
+            parent_vtxindex: 0x4342,
+            key_block_backptr: 0x5150,
+            key_vtxindex: 0x6160,
+            epoch_num: 0x71706362,
+            memo: vec![],
+            pow_nonce: Some(0xdeadbeef),
+        };
+
+        let encoded = to_versioned_bytes(&parsed);
+        let decoded: ParsedData = from_versioned_bytes(&encoded).unwrap();
+
+        assert_eq!(decoded.pow_nonce, parsed.pow_nonce);
+        assert_eq!(decoded.block_header_hash, parsed.block_header_hash);
+    }
+
+    #[test]
+    fn parse_data_reads_a_pow_commit_when_the_payload_is_long_enough() {
+        let mut data = vec![0u8; 76];
+        data.extend_from_slice(&0xcafebabeu32.to_be_bytes());
+        assert_eq!(data.len(), POW_PAYLOAD_LEN);
+
+        let parsed = LeaderBlockCommitOp::parse_data(&data).unwrap();
+        assert_eq!(parsed.pow_nonce, Some(0xcafebabe));
+        assert_eq!(parsed.memo, Vec::<u8>::new());
+    }
+
+    #[test]
+    fn parse_data_reads_a_pob_commit_when_the_payload_ends_at_the_memo_byte() {
+        let mut data = vec![0u8; 76];
+        data.push(0x2a);
+
+        let parsed = LeaderBlockCommitOp::parse_data(&data).unwrap();
+        assert_eq!(parsed.pow_nonce, None);
+        assert_eq!(parsed.memo, vec![0x2a]);
+    }
+
+    #[test]
+    fn multiply_and_divide_target_round_trip_evenly() {
+        let target = [0x10u8; 32];
+        assert_eq!(multiply_target(&divide_target(&target, 4), 4), target);
+    }
+
+    #[test]
+    fn multiply_target_saturates_instead_of_overflowing() {
+        assert_eq!(multiply_target(&[0xff; 32], 2), [0xff; 32]);
+    }
+
     #[test]
     fn test_parse() {
         let vtxindex = 1;
@@ -533,6 +1012,9 @@ mod tests {
                     block_height: block_height,
                     burn_header_hash: burn_header_hash,
                     fork_segment_id: 0,
+                    pow_nonce: None,
+                    treasury_fee: 23456,
+                    treasury_address: Some(StacksAddress::from_bitcoin_address(&BitcoinAddress::from_scriptpubkey(BitcoinNetworkType::Testnet, &hex_bytes("76a9140be3e286a15ea85882761618e366586b5574100d88ac").unwrap()).unwrap())),
                 })
             },
             OpFixture {
@@ -567,6 +1049,7 @@ mod tests {
                         parent_fork_segment_id: op.fork_segment_id,
                         fork_segment_length: 1,
                         fork_length: 1,
+                        op_mr: DoubleSha256([0u8; 32]),
                     }
                 },
                 None => {
@@ -579,6 +1062,7 @@ mod tests {
                         parent_fork_segment_id: 0,
                         fork_segment_length: 0,
                         fork_length: 0,
+                        op_mr: DoubleSha256([0u8; 32]),
                     }
                 }
             };
@@ -630,9 +1114,15 @@ mod tests {
             consensus_hash_lifetime: 24,
             stable_confirmations: 7,
             first_block_height: first_block_height,
-            first_block_hash: first_burn_hash.clone()
+            first_block_hash: first_burn_hash.clone(),
+            treasury_address: None,
+            treasury_numerator: 0,
+            treasury_denominator: 0,
+            treasury_activation_height: 0,
         };
-        
+
+        let epochs = EpochList::permissive();
+
         let mut db = BurnDB::connect_memory(first_block_height, &first_burn_hash).unwrap();
 
         {
@@ -714,6 +1204,9 @@ mod tests {
             block_height: 125,
             burn_header_hash: block_125_hash.clone(),
             fork_segment_id: 0,
+            pow_nonce: None,
+            treasury_fee: 0,
+            treasury_address: None,
         };
 
         {
@@ -753,6 +1246,9 @@ mod tests {
                     block_height: 80,
                     burn_header_hash: block_126_hash.clone(),
                     fork_segment_id: 0,
+                    pow_nonce: None,
+                    treasury_fee: 0,
+                    treasury_address: None,
                 },
                 res: Err(op_error::BlockCommitPredatesGenesis),
             },
@@ -782,6 +1278,9 @@ mod tests {
                     block_height: 126,
                     burn_header_hash: block_126_hash.clone(),
                     fork_segment_id: 0,
+                    pow_nonce: None,
+                    treasury_fee: 0,
+                    treasury_address: None,
                 },
                 res: Err(op_error::BlockCommitBadEpoch),
             },
@@ -811,6 +1310,9 @@ mod tests {
                     block_height: 126,
                     burn_header_hash: block_126_hash.clone(),
                     fork_segment_id: 0,
+                    pow_nonce: None,
+                    treasury_fee: 0,
+                    treasury_address: None,
                 },
                 res: Err(op_error::BlockCommitNoLeaderKey),
             },
@@ -840,6 +1342,9 @@ mod tests {
                     block_height: 126,
                     burn_header_hash: block_126_hash.clone(),
                     fork_segment_id: 0,
+                    pow_nonce: None,
+                    treasury_fee: 0,
+                    treasury_address: None,
                 },
                 res: Err(op_error::BlockCommitLeaderKeyAlreadyUsed),
             },
@@ -869,6 +1374,9 @@ mod tests {
                     block_height: 126,
                     burn_header_hash: block_126_hash.clone(),
                     fork_segment_id: 0,
+                    pow_nonce: None,
+                    treasury_fee: 0,
+                    treasury_address: None,
                 },
                 res: Err(op_error::BlockCommitNoParent),
             },
@@ -898,6 +1406,9 @@ mod tests {
                     block_height: 126,
                     burn_header_hash: block_126_hash.clone(),
                     fork_segment_id: 0,
+                    pow_nonce: None,
+                    treasury_fee: 0,
+                    treasury_address: None,
                 },
                 res: Err(op_error::BlockCommitNoParent),
             },
@@ -927,6 +1438,9 @@ mod tests {
                     block_height: 126,
                     burn_header_hash: block_126_hash.clone(),
                     fork_segment_id: 0,
+                    pow_nonce: None,
+                    treasury_fee: 0,
+                    treasury_address: None,
                 },
                 res: Err(op_error::BlockCommitBadInput),
             },
@@ -956,6 +1470,9 @@ mod tests {
                     block_height: 126,
                     burn_header_hash: block_126_hash.clone(),
                     fork_segment_id: 0,
+                    pow_nonce: None,
+                    treasury_fee: 0,
+                    treasury_address: None,
                 },
                 res: Err(op_error::BlockCommitBadInput)
             },
@@ -985,6 +1502,43 @@ mod tests {
                     block_height: 126,
                     burn_header_hash: block_126_hash.clone(),
                     fork_segment_id: 0,
+                    pow_nonce: None,
+                    treasury_fee: 0,
+                    treasury_address: None,
+                },
+                res: Ok(())
+            },
+            CheckFixture {
+                // accept -- a PoW commit whose nonce beats the retargeted difficulty (the
+                // fixture chain's burn history is tiny, so the retargeted target is the
+                // easiest possible one and any nonce will do)
+                op: LeaderBlockCommitOp {
+                    block_header_hash: BlockHeaderHash::from_bytes(&hex_bytes("2222222222222222222222222222222222222222222222222222222222222222").unwrap()).unwrap(),
+                    new_seed: VRFSeed::from_bytes(&hex_bytes("3333333333333333333333333333333333333333333333333333333333333333").unwrap()).unwrap(),
+                    parent_block_backptr: 1,
+                    parent_vtxindex: 444,
+                    key_block_backptr: 2,
+                    key_vtxindex: 457,
+                    epoch_num: (126 - first_block_height) as u32,
+                    memo: vec![],
+
+                    burn_fee: 12345,
+                    input: BurnchainSigner {
+                        public_keys: vec![
+                            StacksPublicKey::from_hex("02d8015134d9db8178ac93acbc43170a2f20febba5087a5b0437058765ad5133d0").unwrap(),
+                        ],
+                        num_sigs: 1,
+                        hash_mode: AddressHashMode::SerializeP2PKH
+                    },
+
+                    txid: Txid::from_bytes_be(&hex_bytes("3c07a0a93360bc85047bbaadd49e30c8af770f73a37e10fec400174d2e5f27cf").unwrap()).unwrap(),
+                    vtxindex: 445,
+                    block_height: 126,
+                    burn_header_hash: block_126_hash.clone(),
+                    fork_segment_id: 0,
+                    pow_nonce: Some(42),
+                    treasury_fee: 0,
+                    treasury_address: None,
                 },
                 res: Ok(())
             },
@@ -1014,6 +1568,9 @@ mod tests {
                     block_height: 126,
                     burn_header_hash: block_126_hash.clone(),
                     fork_segment_id: 1,
+                    pow_nonce: None,
+                    treasury_fee: 0,
+                    treasury_address: None,
                 },
                 res: Ok(())
             },
@@ -1043,6 +1600,9 @@ mod tests {
                     block_height: 126,
                     burn_header_hash: block_126_hash.clone(),
                     fork_segment_id: 0,
+                    pow_nonce: None,
+                    treasury_fee: 0,
+                    treasury_address: None,
                 },
                 res: Ok(())
             }
@@ -1059,9 +1619,180 @@ mod tests {
                 parent_fork_segment_id: fixture.op.fork_segment_id,
                 fork_segment_length: 1,
                 fork_length: 1,
+                op_mr: DoubleSha256([0u8; 32]),
             };
-            assert_eq!(fixture.res, fixture.op.check(&burnchain, &header, &mut tx));
+            assert_eq!(fixture.res, fixture.op.check(&burnchain, &header, &epochs, &mut tx));
+        }
+    }
+
+    #[test]
+    fn test_check_treasury_burn_rule() {
+        let first_block_height = 121;
+        let first_burn_hash = BurnchainHeaderHash::from_hex("0000000000000000000000000000000000000000000000000000000000000123").unwrap();
+
+        let block_122_hash = BurnchainHeaderHash::from_hex("0000000000000000000000000000000000000000000000000000000000001220").unwrap();
+        let block_123_hash = BurnchainHeaderHash::from_hex("0000000000000000000000000000000000000000000000000000000000001230").unwrap();
+        let block_124_hash = BurnchainHeaderHash::from_hex("0000000000000000000000000000000000000000000000000000000000001240").unwrap();
+        let block_125_hash = BurnchainHeaderHash::from_hex("0000000000000000000000000000000000000000000000000000000000001250").unwrap();
+        let block_126_hash = BurnchainHeaderHash::from_hex("0000000000000000000000000000000000000000000000000000000000001260").unwrap();
+
+        let block_header_hashes = [
+            block_122_hash.clone(),
+            block_123_hash.clone(),
+            block_124_hash.clone(),
+            block_125_hash.clone(),
+            block_126_hash.clone()
+        ];
+
+        let treasury_address = StacksAddress::from_bitcoin_address(&BitcoinAddress::from_scriptpubkey(BitcoinNetworkType::Testnet, &hex_bytes("76a914306231b2782b5f80d944bf69f9d46a1453a0a0eb88ac").unwrap()).unwrap());
+        let wrong_address = StacksAddress::from_bitcoin_address(&BitcoinAddress::from_scriptpubkey(BitcoinNetworkType::Testnet, &hex_bytes("76a914000000000000000000000000000000000000000088ac").unwrap()).unwrap());
+
+        // the treasury rule is inactive until block 126, and takes a tenth of the burn fee
+        let burnchain = Burnchain {
+            peer_version: 0x012345678,
+            network_id: 0x9abcdef0,
+            chain_name: "bitcoin".to_string(),
+            network_name: "testnet".to_string(),
+            working_dir: "/nope".to_string(),
+            consensus_hash_lifetime: 24,
+            stable_confirmations: 7,
+            first_block_height: first_block_height,
+            first_block_hash: first_burn_hash.clone(),
+            treasury_address: Some(treasury_address.clone()),
+            treasury_numerator: 1,
+            treasury_denominator: 10,
+            treasury_activation_height: 126,
+        };
+
+        let epochs = EpochList::permissive();
+
+        let mut db = BurnDB::connect_memory(first_block_height, &first_burn_hash).unwrap();
+
+        {
+            let mut tx = db.tx_begin().unwrap();
+            let mut prev_snapshot = BurnDB::get_first_block_snapshot(&mut tx).unwrap();
+            for i in 0..block_header_hashes.len() {
+                let snapshot_row = BlockSnapshot {
+                    block_height: (i + 1 + first_block_height as usize) as u64,
+                    burn_header_hash: block_header_hashes[i].clone(),
+                    parent_burn_header_hash: prev_snapshot.burn_header_hash.clone(),
+                    consensus_hash: ConsensusHash::from_bytes(&[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,i as u8]).unwrap(),
+                    ops_hash: OpsHash::from_bytes(&[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,i as u8]).unwrap(),
+                    total_burn: i as u64,
+                    sortition: true,
+                    sortition_hash: SortitionHash::initial(),
+                    winning_block_txid: Txid::from_hex("0000000000000000000000000000000000000000000000000000000000000000").unwrap(),
+                    winning_block_burn_hash: BurnchainHeaderHash::from_hex("0000000000000000000000000000000000000000000000000000000000000000").unwrap(),
+
+                    fork_segment_id: 0,
+                    parent_fork_segment_id: 0,
+                    fork_segment_length: (i + 1) as u64,
+                    fork_length: (i + 1) as u64
+                };
+                BurnDB::append_chain_tip_snapshot(&mut tx, &prev_snapshot, &snapshot_row).unwrap();
+                prev_snapshot = snapshot_row;
+            }
+
+            tx.commit().unwrap();
+        }
+
+        let leader_key = LeaderKeyRegisterOp {
+            consensus_hash: ConsensusHash::from_bytes(&hex_bytes("2222222222222222222222222222222222222222").unwrap()).unwrap(),
+            public_key: VRFPublicKey::from_bytes(&hex_bytes("a366b51292bef4edd64063d9145c617fec373bceb0758e98cd72becd84d54c7a").unwrap()).unwrap(),
+            memo: vec![01, 02, 03, 04, 05],
+            address: StacksAddress::from_bitcoin_address(&BitcoinAddress::from_scriptpubkey(BitcoinNetworkType::Testnet, &hex_bytes("76a914306231b2782b5f80d944bf69f9d46a1453a0a0eb88ac").unwrap()).unwrap()),
+
+            txid: Txid::from_bytes_be(&hex_bytes("1bfa831b5fc56c858198acb8e77e5863c1e9d8ac26d49ddb914e24d8d4083562").unwrap()).unwrap(),
+            vtxindex: 456,
+            block_height: 124,
+            burn_header_hash: block_124_hash.clone(),
+            fork_segment_id: 0,
+        };
+
+        {
+            let mut tx = db.tx_begin().unwrap();
+            BurnDB::insert_leader_key(&mut tx, &leader_key).unwrap();
+            tx.commit().unwrap();
         }
+
+        let base_op = LeaderBlockCommitOp {
+            block_header_hash: BlockHeaderHash::from_bytes(&hex_bytes("2222222222222222222222222222222222222222222222222222222222222222").unwrap()).unwrap(),
+            new_seed: VRFSeed::from_bytes(&hex_bytes("3333333333333333333333333333333333333333333333333333333333333333").unwrap()).unwrap(),
+            parent_block_backptr: 0,
+            parent_vtxindex: 0,
+            key_block_backptr: 2,
+            key_vtxindex: 456,
+            epoch_num: (126 - first_block_height) as u32,
+            memo: vec![0x80],
+
+            burn_fee: 12345,
+            input: BurnchainSigner {
+                public_keys: vec![
+                    StacksPublicKey::from_hex("02d8015134d9db8178ac93acbc43170a2f20febba5087a5b0437058765ad5133d0").unwrap(),
+                ],
+                num_sigs: 1,
+                hash_mode: AddressHashMode::SerializeP2PKH
+            },
+
+            txid: Txid::from_bytes_be(&hex_bytes("3c07a0a93360bc85047bbaadd49e30c8af770f73a37e10fec400174d2e5f27cf").unwrap()).unwrap(),
+            vtxindex: 445,
+            block_height: 126,
+            burn_header_hash: block_126_hash.clone(),
+            fork_segment_id: 0,
+            pow_nonce: None,
+            treasury_fee: 0,
+            treasury_address: None,
+        };
+
+        let mut tx = db.tx_begin().unwrap();
+        let header = BurnchainBlockHeader {
+            block_height: base_op.block_height,
+            block_hash: base_op.burn_header_hash.clone(),
+            parent_block_hash: base_op.burn_header_hash.clone(),
+            num_txs: 1,
+            fork_segment_id: base_op.fork_segment_id,
+            parent_fork_segment_id: base_op.fork_segment_id,
+            fork_segment_length: 1,
+            fork_length: 1,
+            op_mr: DoubleSha256([0u8; 32]),
+        };
+
+        // reject -- treasury fee is below the required tenth of burn_fee
+        let mut underpaid = base_op.clone();
+        underpaid.treasury_fee = 100;
+        underpaid.treasury_address = Some(treasury_address.clone());
+        assert_eq!(underpaid.check(&burnchain, &header, &epochs, &mut tx), Err(op_error::BlockCommitInsufficientTreasuryBurn));
+
+        // reject -- treasury fee meets the fraction but pays the wrong address
+        let mut misdirected = base_op.clone();
+        misdirected.treasury_fee = 1235;
+        misdirected.treasury_address = Some(wrong_address);
+        assert_eq!(misdirected.check(&burnchain, &header, &epochs, &mut tx), Err(op_error::BlockCommitInsufficientTreasuryBurn));
+
+        // accept -- treasury fee meets the configured fraction and pays the configured address
+        let mut paid = base_op.clone();
+        paid.treasury_fee = 1235;
+        paid.treasury_address = Some(treasury_address.clone());
+        assert_eq!(paid.check(&burnchain, &header, &epochs, &mut tx), Ok(()));
+
+        // reject -- a misconfigured burnchain (treasury_address set, but treasury_denominator
+        // left at 0) must fail cleanly instead of panicking on a division by zero
+        let zero_denominator_burnchain = Burnchain {
+            peer_version: 0x012345678,
+            network_id: 0x9abcdef0,
+            chain_name: "bitcoin".to_string(),
+            network_name: "testnet".to_string(),
+            working_dir: "/nope".to_string(),
+            consensus_hash_lifetime: 24,
+            stable_confirmations: 7,
+            first_block_height: first_block_height,
+            first_block_hash: first_burn_hash.clone(),
+            treasury_address: Some(treasury_address.clone()),
+            treasury_numerator: 1,
+            treasury_denominator: 0,
+            treasury_activation_height: 126,
+        };
+        assert_eq!(paid.check(&zero_denominator_burnchain, &header, &epochs, &mut tx), Err(op_error::InvalidInput));
     }
 }
 
@@ -31,6 +31,7 @@ use chainstate::burn::operations::{
 
 use util::db::DBConn;
 use util::db::DBTx;
+use util::mmr::{MerkleMountainRange, MMRInclusionProof, MMRHash, build_proof, verify_proof};
 
 use chainstate::burn::db::burndb::BurnDB;
 
@@ -51,14 +52,59 @@ use chainstate::stacks::StacksPrivateKey;
 use util::vrf::{VRF,VRFPublicKey,VRFPrivateKey};
 
 use util::log;
+use util::hash::{to_hex, hex_bytes};
 use util::hash::DoubleSha256;
 
+use chainstate::burn::operations::serialization::{Serializable, Deserializable};
+use chainstate::burn::operations::epoch::EpochList;
+
 struct ParsedData {
     pub consensus_hash: ConsensusHash,
     pub public_key: VRFPublicKey,
     pub memo: Vec<u8>
 }
 
+impl ParsedData {
+    // Shared by `LeaderKeyRegisterOp::parse_data` (fixed-offset OP_RETURN layout) and the
+    // `Deserializable` impl below (versioned envelope) -- both ultimately read the same
+    // `consensus hash || public key || memo` byte layout.
+    fn from_bytes(data: &Vec<u8>) -> Option<ParsedData> {
+        if data.len() < 52 {
+            return None;
+        }
+
+        let consensus_hash = ConsensusHash::from_bytes(&data[0..20]).expect("FATAL: invalid byte slice for consensus hash");
+        let pubkey = VRFPublicKey::from_bytes(&data[20..52].to_vec())?;
+        let memo = &data[52..];
+
+        Some(ParsedData {
+            consensus_hash,
+            public_key: pubkey,
+            memo: memo.to_vec()
+        })
+    }
+}
+
+impl Serializable for ParsedData {
+    // Mirrors the fixed-offset OP_RETURN layout read by `parse_data` below, but wrapped in
+    // the versioned envelope (see `chainstate::burn::operations::serialization`) for contexts
+    // that move these payloads outside of a burnchain OP_RETURN -- e.g. a relay mempool, or a
+    // cross-client API -- where a raw fixed-offset blob can't describe its own format.
+    fn serialize(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(52 + self.memo.len());
+        bytes.extend_from_slice(self.consensus_hash.as_bytes());
+        bytes.extend_from_slice(&self.public_key.as_bytes());
+        bytes.extend_from_slice(&self.memo);
+        bytes
+    }
+}
+
+impl Deserializable for ParsedData {
+    fn deserialize(_version: u8, bytes: &[u8]) -> Result<ParsedData, op_error> {
+        ParsedData::from_bytes(&bytes.to_vec()).ok_or(op_error::ParseError)
+    }
+}
+
 impl LeaderKeyRegisterOp {
     #[cfg(test)]
     pub fn new(sender: &StacksAddress, public_key: &VRFPublicKey) -> LeaderKeyRegisterOp {
@@ -110,6 +156,67 @@ impl LeaderKeyRegisterOp {
         self.fork_segment_id = block_header.fork_segment_id;
     }
 
+    /// Builds this op's canonical OP_RETURN payload bytes -- the same bytes `to_ledger_csv_row`
+    /// hex-encodes and a registration transaction ultimately carries. Exposed so a signer
+    /// (in-process or an external device via `burnchains::signing::BurnSigningDevice`) always
+    /// signs exactly what ends up on the burnchain.
+    pub fn preimage(&self) -> Vec<u8> {
+        let payload = ParsedData {
+            consensus_hash: self.consensus_hash.clone(),
+            public_key: self.public_key.clone(),
+            memo: self.memo.clone(),
+        };
+
+        payload.serialize()
+    }
+
+    /// Serializes this op as one row of the burn-op ledger CSV (see
+    /// `chainstate::burn::operations::ledger_csv`): the envelope fields a reader needs to
+    /// place the op on a fork, followed by this op's OP_RETURN payload as `preimage()` builds
+    /// it, hex-encoded without the version byte `serialization::to_versioned_bytes` would
+    /// prepend. `from_ledger_csv_row` reverses this exactly.
+    pub fn to_ledger_csv_row(&self) -> String {
+        format!(
+            "leader_key_register,{},{},{},{},{},{},{}",
+            to_hex(self.txid.as_bytes()),
+            self.vtxindex,
+            self.block_height,
+            to_hex(self.burn_header_hash.as_bytes()),
+            self.fork_segment_id,
+            to_hex(&self.address.to_bytes()),
+            to_hex(&self.preimage()),
+        )
+    }
+
+    /// Reconstructs a `LeaderKeyRegisterOp` from one row written by `to_ledger_csv_row`.
+    pub fn from_ledger_csv_row(row: &str) -> Result<LeaderKeyRegisterOp, op_error> {
+        let fields: Vec<&str> = row.split(',').collect();
+        if fields.len() != 8 || fields[0] != "leader_key_register" {
+            return Err(op_error::ParseError);
+        }
+
+        let txid = Txid::from_hex(fields[1]).map_err(|_| op_error::ParseError)?;
+        let vtxindex: u32 = fields[2].parse().map_err(|_| op_error::ParseError)?;
+        let block_height: u64 = fields[3].parse().map_err(|_| op_error::ParseError)?;
+        let burn_header_hash = BurnchainHeaderHash::from_hex(fields[4]).map_err(|_| op_error::ParseError)?;
+        let fork_segment_id: u64 = fields[5].parse().map_err(|_| op_error::ParseError)?;
+        let address_bytes = hex_bytes(fields[6]).map_err(|_| op_error::ParseError)?;
+        let address = StacksAddress::from_bytes(&address_bytes).ok_or(op_error::ParseError)?;
+        let payload_bytes = hex_bytes(fields[7]).map_err(|_| op_error::ParseError)?;
+        let payload = ParsedData::from_bytes(&payload_bytes).ok_or(op_error::ParseError)?;
+
+        Ok(LeaderKeyRegisterOp {
+            consensus_hash: payload.consensus_hash,
+            public_key: payload.public_key,
+            memo: payload.memo,
+            address,
+            txid,
+            vtxindex,
+            block_height,
+            burn_header_hash,
+            fork_segment_id,
+        })
+    }
 
     fn parse_data(data: &Vec<u8>) -> Option<ParsedData> {
         /*
@@ -122,35 +229,31 @@ impl LeaderKeyRegisterOp {
             
              Note that `data` is missing the first 3 bytes -- the magic and op have been stripped
         */
-        // memo can be empty, and magic + op are omitted 
+        // memo can be empty, and magic + op are omitted
         if data.len() < 52 {
             // too short to have a consensus hash and proving public key
             warn!("LEADER_KEY_REGISTER payload is malformed ({} bytes)", data.len());
             return None;
         }
 
-        let consensus_hash = ConsensusHash::from_bytes(&data[0..20]).expect("FATAL: invalid byte slice for consensus hash");
-        let pubkey = match VRFPublicKey::from_bytes(&data[20..52].to_vec()) {
-            Some(pubk) => {
-                pubk
-            },
+        match ParsedData::from_bytes(data) {
+            Some(parsed) => Some(parsed),
             None => {
                 warn!("Invalid VRF public key");
-                return None;
+                None
             }
-        };
-
-        let memo = &data[52..];
-
-        Some(ParsedData {
-            consensus_hash,
-            public_key: pubkey,
-            memo: memo.to_vec()
-        })
+        }
     }
 
     fn parse_from_tx(block_height: u64, fork_segment_id: u64, block_hash: &BurnchainHeaderHash, tx: &BurnchainTransaction) -> Result<LeaderKeyRegisterOp, op_error> {
         // can't be too careful...
+        // `tx.get_signers()` resolves each input's signer via
+        // `BitcoinBlockParser::recover_signer_from_input`, which reads the scriptSig when
+        // present and otherwise falls back to the witness stack (see
+        // `burnchains::bitcoin::witness`) -- so this works for txs broadcast from either legacy
+        // or P2WPKH/P2WSH wallets. A segwit input whose witness program couldn't be resolved to
+        // a public key comes back as a signer with no keys, which we must still reject here
+        // (rather than silently registering a key we can't attribute).
         let inputs = tx.get_signers();
         let outputs = tx.get_recipients();
 
@@ -159,6 +262,11 @@ impl LeaderKeyRegisterOp {
             return Err(op_error::InvalidInput);
         }
 
+        if inputs[0].public_keys.len() == 0 {
+            test_debug!("Invalid tx: could not recover a signer public key from input 0");
+            return Err(op_error::InvalidInput);
+        }
+
         if outputs.len() < 1 {
             test_debug!("Invalid tx: inputs: {}, outputs: {}", inputs.len(), outputs.len());
             return Err(op_error::InvalidInput);
@@ -202,41 +310,187 @@ impl BlockstackOperation for LeaderKeyRegisterOp {
         LeaderKeyRegisterOp::parse_from_tx(block_header.block_height, block_header.fork_segment_id, &block_header.block_hash, tx)
     }
 
-    fn check<'a>(&self, burnchain: &Burnchain, block_header: &BurnchainBlockHeader, tx: &mut DBTx<'a>) -> Result<(), op_error> {
+    // `_epochs` is accepted so this impl stays in step with `BlockstackOperation::check`'s
+    // signature, but a leader key registration has no epoch-gated rules of its own yet -- unlike
+    // `LeaderBlockCommitOp::check`, which looks params up in it (see
+    // `chainstate::burn::operations::epoch`).
+    fn check<'a>(&self, burnchain: &Burnchain, block_header: &BurnchainBlockHeader, _epochs: &EpochList, tx: &mut DBTx<'a>) -> Result<(), op_error> {
+        self.check_impl(burnchain, block_header, tx, None, None)
+    }
+}
+
+impl LeaderKeyRegisterOp {
+    /// Pruned-mode counterpart to `check()`: every other validation this op requires (genesis
+    /// floor) is identical, but rather than looking up consensus hash freshness and VRF-key
+    /// uniqueness against the full per-block / per-key history, the caller supplies an MMR
+    /// inclusion proof against the fork segment's consensus-hash MMR peak set and a
+    /// `VRFKeyUniquenessProof` against its VRF-public-key MMR peak set (see `util::mmr`) --
+    /// letting a pruned node validate a leader key registration without keeping every block's
+    /// consensus hash or every previously-registered key around, the same tradeoff
+    /// `LeaderBlockCommitOp::check_with_accumulator_proof` makes for leader keys via
+    /// `chainstate::burn::operations::accumulator`.
+    pub fn check_with_mmr_proof<'a>(&self, burnchain: &Burnchain, block_header: &BurnchainBlockHeader, tx: &mut DBTx<'a>, consensus_hash_proof: &MMRInclusionProof, vrf_key_uniqueness_proof: &VRFKeyUniquenessProof) -> Result<(), op_error> {
+        self.check_impl(burnchain, block_header, tx, Some(consensus_hash_proof), Some(vrf_key_uniqueness_proof))
+    }
+
+    fn check_impl<'a>(&self, burnchain: &Burnchain, block_header: &BurnchainBlockHeader, tx: &mut DBTx<'a>, consensus_hash_proof: Option<&MMRInclusionProof>, vrf_key_uniqueness_proof: Option<&VRFKeyUniquenessProof>) -> Result<(), op_error> {
+        /////////////////////////////////////////////////////////////////
+        // This op can't predate the network's configured first block --
+        // e.g. a devnet chain that's been reset and rewound below where it used to be
+        /////////////////////////////////////////////////////////////////
+        if self.block_height < burnchain.first_block_height {
+            warn!("Invalid leader key registration: predates first block height {}", burnchain.first_block_height);
+            return Err(op_error::LeaderKeyPredatesGenesis);
+        }
+
         // this will be the chain tip we're building on
         let chain_tip = BurnDB::get_block_snapshot(tx, &block_header.parent_block_hash)
             .expect("FATAL: failed to query parent block snapshot")
             .expect("FATAL: no parent snapshot in the DB");
 
         /////////////////////////////////////////////////////////////////
-        // Keys must be unique -- no one can register the same key twice
+        // Keys must be unique -- no one can register the same key twice. Verified against the
+        // fork segment's VRF-public-key MMR (see `VRFKeyUniquenessProof`) rather than a direct
+        // per-key lookup, so the same accumulator a pruned node relies on is also what a full
+        // node's own check runs against.
         /////////////////////////////////////////////////////////////////
 
-        // key selected here must never have been submitted on this fork before 
-        let has_key_already = BurnDB::has_VRF_public_key(tx, &self.public_key, chain_tip.fork_segment_id)
-            .expect("Sqlite failure while fetching VRF public key");
+        match vrf_key_uniqueness_proof {
+            None => {
+                // Full node: it holds every key ever registered on this fork, so it can compute
+                // the uniqueness proof itself (sorted by public key, the order the VRF-key MMR
+                // is built in) rather than require the caller to supply one.
+                let mut registered_keys = BurnDB::get_all_registered_vrf_public_keys(tx, chain_tip.fork_segment_id)
+                    .expect("Sqlite failure while fetching registered VRF public keys");
+                registered_keys.sort_by(|a, b| a.as_bytes().cmp(&b.as_bytes()));
+
+                let proof = build_vrf_key_uniqueness_proof(&registered_keys, &self.public_key);
+                let vrf_key_peaks = vrf_key_mmr_peaks(&registered_keys);
+
+                if !verify_vrf_key_uniqueness(&self.public_key, &proof, &vrf_key_peaks) {
+                    warn!("Invalid leader key registration: public key {} previously used", &self.public_key.to_hex());
+                    return Err(op_error::LeaderKeyAlreadyRegistered);
+                }
+            },
+            Some(proof) => {
+                let vrf_key_peaks = BurnDB::get_vrf_public_key_mmr_peaks(tx, chain_tip.fork_segment_id)
+                    .expect("Sqlite failure while fetching the VRF-public-key MMR peak set");
 
-        if has_key_already {
-            warn!("Invalid leader key registration: public key {} previously used", &self.public_key.to_hex());
-            return Err(op_error::LeaderKeyAlreadyRegistered);
+                if !verify_vrf_key_uniqueness(&self.public_key, proof, &vrf_key_peaks) {
+                    warn!("Invalid leader key registration: public key {} failed MMR uniqueness proof in fork {}", &self.public_key.to_hex(), chain_tip.fork_segment_id);
+                    return Err(op_error::LeaderKeyAlreadyRegistered);
+                }
+            },
         }
 
         /////////////////////////////////////////////////////////////////
         // Consensus hash must be recent and valid
         /////////////////////////////////////////////////////////////////
 
-        let consensus_hash_recent = BurnDB::is_fresh_consensus_hash(tx, chain_tip.block_height, burnchain.consensus_hash_lifetime.into(), &self.consensus_hash, chain_tip.fork_segment_id)
-            .expect("Sqlite failure while checking consensus hash freshness");
+        match consensus_hash_proof {
+            None => {
+                let consensus_hash_recent = BurnDB::is_fresh_consensus_hash(tx, chain_tip.block_height, burnchain.consensus_hash_lifetime.into(), &self.consensus_hash, chain_tip.fork_segment_id)
+                    .expect("Sqlite failure while checking consensus hash freshness");
+
+                if !consensus_hash_recent {
+                    warn!("Invalid leader key registration: invalid consensus hash {}", &self.consensus_hash.to_hex());
+                    return Err(op_error::LeaderKeyBadConsensusHash);
+                }
+            },
+            Some(proof) => {
+                let consensus_hash_peaks = BurnDB::get_consensus_hash_mmr_peaks(tx, chain_tip.fork_segment_id)
+                    .expect("Sqlite failure while fetching the consensus-hash MMR peak set");
 
-        if !consensus_hash_recent {
-            warn!("Invalid leader key registration: invalid consensus hash {}", &self.consensus_hash.to_hex());
-            return Err(op_error::LeaderKeyBadConsensusHash);
+                if !verify_proof(self.consensus_hash.as_bytes(), proof, &consensus_hash_peaks) {
+                    warn!("Invalid leader key registration: consensus hash {} failed MMR inclusion proof in fork {}", &self.consensus_hash.to_hex(), chain_tip.fork_segment_id);
+                    return Err(op_error::LeaderKeyBadConsensusHash);
+                }
+            },
         }
 
         Ok(())
     }
 }
 
+/// A proof that no fork segment has a `VRFPublicKey` registered equal to a given candidate,
+/// expressed over that fork segment's VRF-public-key MMR (whose leaves are every registered key,
+/// kept in ascending byte order). A plain MMR inclusion proof can only show a leaf *is* present,
+/// not that one is absent -- so non-membership is shown instead by proving inclusion of the
+/// candidate's immediate neighbors in that sorted leaf order: if both neighbors check out and
+/// the candidate falls strictly between them (or there's no registered key on one or both
+/// sides), no registered leaf can be the candidate itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VRFKeyUniquenessProof {
+    pub predecessor: Option<(VRFPublicKey, MMRInclusionProof)>,
+    pub successor: Option<(VRFPublicKey, MMRInclusionProof)>,
+}
+
+/// Builds the peak set of the VRF-public-key MMR for a fork segment: its registered keys, in
+/// ascending byte order, appended one at a time the same way any other MMR leaf is.
+fn vrf_key_mmr_peaks(sorted_keys: &[VRFPublicKey]) -> Vec<MMRHash> {
+    let mut mmr = MerkleMountainRange::new();
+    for key in sorted_keys.iter() {
+        mmr.append(&key.as_bytes());
+    }
+    mmr.peaks().to_vec()
+}
+
+/// Builds a `VRFKeyUniquenessProof` that `candidate` isn't among `sorted_keys` (which must
+/// already be in the ascending byte order the VRF-public-key MMR is built over): finds
+/// `candidate`'s immediate neighbors in that order and proves each one's inclusion under the MMR
+/// built from `sorted_keys`.
+pub fn build_vrf_key_uniqueness_proof(sorted_keys: &[VRFPublicKey], candidate: &VRFPublicKey) -> VRFKeyUniquenessProof {
+    let leaves: Vec<Vec<u8>> = sorted_keys.iter().map(|key| key.as_bytes().to_vec()).collect();
+    let candidate_bytes = candidate.as_bytes();
+
+    let predecessor_index = sorted_keys.iter().rposition(|key| key.as_bytes() < candidate_bytes);
+    let successor_index = sorted_keys.iter().position(|key| key.as_bytes() > candidate_bytes);
+
+    VRFKeyUniquenessProof {
+        predecessor: predecessor_index.map(|i| {
+            let proof = build_proof(&leaves, i).expect("FATAL: VRF-public-key MMR leaf/index invariant violated");
+            (sorted_keys[i].clone(), proof)
+        }),
+        successor: successor_index.map(|i| {
+            let proof = build_proof(&leaves, i).expect("FATAL: VRF-public-key MMR leaf/index invariant violated");
+            (sorted_keys[i].clone(), proof)
+        }),
+    }
+}
+
+/// Verifies a `VRFKeyUniquenessProof`: each neighbor it names must actually be on the claimed
+/// side of `candidate`, must check out as an MMR inclusion proof against `peaks`, and must sit
+/// right next to the other neighbor in leaf order (a lone predecessor must be the last leaf, a
+/// lone successor the first), so no registered leaf lies between them. An empty
+/// proof (no predecessor and no successor) is only valid when the MMR itself has no peaks yet,
+/// i.e. no key has ever been registered on this fork.
+pub fn verify_vrf_key_uniqueness(candidate: &VRFPublicKey, proof: &VRFKeyUniquenessProof, peaks: &[MMRHash]) -> bool {
+    let candidate_bytes = candidate.as_bytes();
+
+    if let Some((key, inclusion)) = &proof.predecessor {
+        if key.as_bytes() >= candidate_bytes || !verify_proof(&key.as_bytes(), inclusion, peaks) {
+            return false;
+        }
+    }
+
+    if let Some((key, inclusion)) = &proof.successor {
+        if key.as_bytes() <= candidate_bytes || !verify_proof(&key.as_bytes(), inclusion, peaks) {
+            return false;
+        }
+    }
+
+    let is_last_in_peak = |p: &MMRInclusionProof| p.siblings.len() < 64 && p.leaf_index.checked_add(1) == Some(1u64 << p.siblings.len());
+    match (&proof.predecessor, &proof.successor) {
+        (None, None) => peaks.is_empty(),
+        (None, Some((_, succ))) => succ.peak_index == 0 && succ.leaf_index == 0,
+        (Some((_, pred)), None) => pred.peak_index + 1 == peaks.len() && is_last_in_peak(pred),
+        (Some((_, pred)), Some((_, succ))) if pred.peak_index == succ.peak_index =>
+            succ.siblings.len() < 64 && succ.leaf_index < (1u64 << succ.siblings.len()) && pred.leaf_index.checked_add(1) == Some(succ.leaf_index),
+        (Some((_, pred)), Some((_, succ))) =>
+            succ.peak_index == pred.peak_index + 1 && is_last_in_peak(pred) && succ.leaf_index == 0,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -264,6 +518,24 @@ mod tests {
         BlockstackOperationType
     };
 
+    use chainstate::burn::operations::serialization::{to_versioned_bytes, from_versioned_bytes};
+
+    #[test]
+    fn parsed_data_roundtrips_through_the_versioned_wire_format() {
+        let parsed = ParsedData {
+            consensus_hash: ConsensusHash::from_bytes(&hex_bytes("2222222222222222222222222222222222222222").unwrap()).unwrap(),
+            public_key: VRFPublicKey::from_bytes(&hex_bytes("a366b51292bef4edd64063d9145c617fec373bceb0758e98cd72becd84d54c7a").unwrap()).unwrap(),
+            memo: vec![1, 2, 3],
+        };
+
+        let encoded = to_versioned_bytes(&parsed);
+        let decoded: ParsedData = from_versioned_bytes(&encoded).unwrap();
+
+        assert_eq!(decoded.consensus_hash, parsed.consensus_hash);
+        assert_eq!(decoded.public_key, parsed.public_key);
+        assert_eq!(decoded.memo, parsed.memo);
+    }
+
     struct OpFixture {
         txstr: String,
         result: Option<LeaderKeyRegisterOp>,
@@ -358,6 +630,7 @@ mod tests {
                         parent_fork_segment_id: op.fork_segment_id,
                         fork_segment_length: 1,
                         fork_length: 1,
+                        op_mr: DoubleSha256([0u8; 32]),
                     }
                 },
                 None => {
@@ -370,6 +643,7 @@ mod tests {
                         parent_fork_segment_id: 0,
                         fork_segment_length: 0,
                         fork_length: 0,
+                        op_mr: DoubleSha256([0u8; 32]),
                     }
                 }
             };
@@ -413,9 +687,15 @@ mod tests {
             consensus_hash_lifetime: 24,
             stable_confirmations: 7,
             first_block_height: first_block_height,
-            first_block_hash: first_burn_hash.clone()
+            first_block_hash: first_burn_hash.clone(),
+            treasury_address: None,
+            treasury_numerator: 0,
+            treasury_denominator: 0,
+            treasury_activation_height: 0,
         };
-        
+
+        let epochs = EpochList::permissive();
+
         let mut db = BurnDB::connect_memory(first_block_height, &first_burn_hash).unwrap();
 
         let leader_key_1 = LeaderKeyRegisterOp { 
@@ -515,6 +795,22 @@ mod tests {
                     fork_segment_id: 0,
                 },
                 res: Ok(())
+            },
+            CheckFixture {
+                // reject -- predates the network's configured first block height
+                op: LeaderKeyRegisterOp {
+                    consensus_hash: ConsensusHash::from_bytes(&hex_bytes("0000000000000000000000000000000000000000").unwrap()).unwrap(),
+                    public_key: VRFPublicKey::from_bytes(&hex_bytes("cc519494643f79f1dea0350e6fb9a1da88dfdb6137117fc2523824a8aa44fe1c").unwrap()).unwrap(),
+                    memo: vec![01, 02, 03, 04, 05],
+                    address: StacksAddress::from_bitcoin_address(&BitcoinAddress::from_scriptpubkey(BitcoinNetworkType::Testnet, &hex_bytes("76a9140be3e286a15ea85882761618e366586b5574100d88ac").unwrap()).unwrap()),
+
+                    txid: Txid::from_bytes_be(&hex_bytes("1bfa831b5fc56c858198acb8e77e5863c1e9d8ac26d49ddb914e24d8d4083562").unwrap()).unwrap(),
+                    vtxindex: 457,
+                    block_height: first_block_height - 1,
+                    burn_header_hash: block_122_hash.clone(),
+                    fork_segment_id: 0,
+                },
+                res: Err(op_error::LeaderKeyPredatesGenesis),
             }
         ];
 
@@ -529,9 +825,52 @@ mod tests {
                 parent_fork_segment_id: fixture.op.fork_segment_id,
                 fork_segment_length: 1,
                 fork_length: 1,
+                op_mr: DoubleSha256([0u8; 32]),
             };
-            assert_eq!(fixture.res, fixture.op.check(&burnchain, &header, &mut tx));
+            assert_eq!(fixture.res, fixture.op.check(&burnchain, &header, &epochs, &mut tx));
         }
     }
+
+    #[test]
+    fn vrf_key_uniqueness_proof_accepts_a_genuinely_new_key_and_rejects_a_duplicate() {
+        let mut sorted_keys: Vec<VRFPublicKey> = vec![
+            VRFPublicKey::from_bytes(&hex_bytes("1111111111111111111111111111111111111111111111111111111111111111").unwrap()).unwrap(),
+            VRFPublicKey::from_bytes(&hex_bytes("5555555555555555555555555555555555555555555555555555555555555555").unwrap()).unwrap(),
+            VRFPublicKey::from_bytes(&hex_bytes("9999999999999999999999999999999999999999999999999999999999999999").unwrap()).unwrap(),
+        ];
+        sorted_keys.sort_by(|a, b| a.as_bytes().cmp(&b.as_bytes()));
+
+        let peaks = vrf_key_mmr_peaks(&sorted_keys);
+
+        // accept -- a key strictly between two already-registered keys is still unused
+        let new_key = VRFPublicKey::from_bytes(&hex_bytes("3333333333333333333333333333333333333333333333333333333333333333").unwrap()).unwrap();
+        let proof = build_vrf_key_uniqueness_proof(&sorted_keys, &new_key);
+        assert!(verify_vrf_key_uniqueness(&new_key, &proof, &peaks));
+
+        // accept -- a key below every registered key
+        let lowest_key = VRFPublicKey::from_bytes(&hex_bytes("0000000000000000000000000000000000000000000000000000000000000000").unwrap()).unwrap();
+        let proof = build_vrf_key_uniqueness_proof(&sorted_keys, &lowest_key);
+        assert!(verify_vrf_key_uniqueness(&lowest_key, &proof, &peaks));
+
+        // accept -- a key above every registered key
+        let highest_key = VRFPublicKey::from_bytes(&hex_bytes("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff").unwrap()).unwrap();
+        let proof = build_vrf_key_uniqueness_proof(&sorted_keys, &highest_key);
+        assert!(verify_vrf_key_uniqueness(&highest_key, &proof, &peaks));
+
+        // reject -- an already-registered key is not unique, no matter what proof is handed in
+        let duplicate = sorted_keys[1].clone();
+        let forged_proof = build_vrf_key_uniqueness_proof(&sorted_keys, &new_key);
+        assert!(!verify_vrf_key_uniqueness(&duplicate, &forged_proof, &peaks));
+    }
+
+    #[test]
+    fn vrf_key_uniqueness_proof_accepts_anything_against_an_empty_mmr() {
+        let sorted_keys: Vec<VRFPublicKey> = vec![];
+        let peaks = vrf_key_mmr_peaks(&sorted_keys);
+
+        let candidate = VRFPublicKey::from_bytes(&hex_bytes("3333333333333333333333333333333333333333333333333333333333333333").unwrap()).unwrap();
+        let proof = build_vrf_key_uniqueness_proof(&sorted_keys, &candidate);
+        assert!(verify_vrf_key_uniqueness(&candidate, &proof, &peaks));
+    }
 }
 
@@ -0,0 +1,265 @@
+/*
+ copyright: (c) 2013-2018 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! A Merkle tree over the burn operations committed to by a single `BurnchainBlockHeader`'s
+//! `op_mr` field, so a caller can prove a single `LeaderBlockCommitOp`/`LeaderKeyRegisterOp`
+//! belongs to a header with `O(log n)` hashes instead of re-scanning every op in the block.
+//!
+//! Leaves are ordered by `vtxindex` -- the same order every other per-block op index in this
+//! crate uses -- and hashed from each op's `preimage()`, the same canonical encoding
+//! `to_ledger_csv_row` commits to. Leaf and internal-node hashes are domain-separated the same
+//! way `util::mmr` separates them, so a leaf can never be replayed as an internal node. An
+//! odd-width level duplicates its last node rather than leaving it unpaired, the same rule
+//! Bitcoin's own transaction Merkle tree uses (see `burnchains::bitcoin::merkle`, which verifies
+//! the analogous tree one level down, over raw transactions rather than parsed ops).
+//!
+//! `UserBurnSupportOp` isn't wired up here yet, matching `ledger_csv`'s own stance on it.
+
+use chainstate::burn::operations::{BlockstackOperationType, Error as op_error};
+
+use util::hash::DoubleSha256;
+
+fn hash_leaf(data: &[u8]) -> DoubleSha256 {
+    let mut buf = vec![0x00]; // leaf domain tag
+    buf.extend_from_slice(data);
+    DoubleSha256::from_data(&buf)
+}
+
+fn hash_node(left: &DoubleSha256, right: &DoubleSha256) -> DoubleSha256 {
+    let mut buf = Vec::with_capacity(65);
+    buf.push(0x01); // internal-node domain tag
+    buf.extend_from_slice(left.as_bytes());
+    buf.extend_from_slice(right.as_bytes());
+    DoubleSha256::from_data(&buf)
+}
+
+fn op_vtxindex(op: &BlockstackOperationType) -> Result<u32, op_error> {
+    match op {
+        BlockstackOperationType::LeaderKeyRegister(op) => Ok(op.vtxindex),
+        BlockstackOperationType::LeaderBlockCommit(op) => Ok(op.vtxindex),
+        BlockstackOperationType::UserBurnSupport(_) => Err(op_error::ParseError),
+    }
+}
+
+/// The leaf hash an op contributes to its block's `op_mr` tree.
+pub fn op_leaf_hash(op: &BlockstackOperationType) -> Result<DoubleSha256, op_error> {
+    let preimage = match op {
+        BlockstackOperationType::LeaderKeyRegister(op) => op.preimage(),
+        BlockstackOperationType::LeaderBlockCommit(op) => op.preimage(),
+        BlockstackOperationType::UserBurnSupport(_) => return Err(op_error::ParseError),
+    };
+    Ok(hash_leaf(&preimage))
+}
+
+fn sorted_leaves(ops: &[BlockstackOperationType]) -> Result<Vec<(u32, DoubleSha256)>, op_error> {
+    let mut indexed = Vec::with_capacity(ops.len());
+    for op in ops {
+        indexed.push((op_vtxindex(op)?, op_leaf_hash(op)?));
+    }
+    indexed.sort_by_key(|(vtxindex, _)| *vtxindex);
+    Ok(indexed)
+}
+
+fn root_of(mut level: Vec<DoubleSha256>) -> DoubleSha256 {
+    while level.len() > 1 {
+        if level.len() % 2 == 1 {
+            let last = level.last().unwrap().clone();
+            level.push(last);
+        }
+        level = level.chunks(2).map(|pair| hash_node(&pair[0], &pair[1])).collect();
+    }
+    level.into_iter().next().expect("FATAL: root_of called with no leaves")
+}
+
+/// An inclusion proof that a single op's leaf is present under an `op_mr` root: the sibling
+/// hash at each level of the leaf's path, from the leaf upward.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpMerkleProof {
+    pub leaf_index: u64,
+    pub siblings: Vec<DoubleSha256>,
+}
+
+/// Builds the `op_mr` Merkle root over `ops`, sorted into `vtxindex` order first. Fails with
+/// `ParseError` if `ops` is empty (there's no root over zero leaves) or contains an op type this
+/// tree doesn't yet commit to.
+pub fn build_op_merkle_root(ops: &[BlockstackOperationType]) -> Result<DoubleSha256, op_error> {
+    let leaves: Vec<DoubleSha256> = sorted_leaves(ops)?.into_iter().map(|(_, h)| h).collect();
+    if leaves.is_empty() {
+        return Err(op_error::ParseError);
+    }
+    Ok(root_of(leaves))
+}
+
+/// Builds an inclusion proof for the op at `target_vtxindex` within `ops`'s `op_mr` tree.
+/// Returns `Ok(None)` if no op in `ops` has that `vtxindex`.
+pub fn build_op_merkle_proof(ops: &[BlockstackOperationType], target_vtxindex: u32) -> Result<Option<OpMerkleProof>, op_error> {
+    let indexed = sorted_leaves(ops)?;
+    let leaf_index = match indexed.iter().position(|(vtxindex, _)| *vtxindex == target_vtxindex) {
+        Some(i) => i,
+        None => return Ok(None),
+    };
+
+    let mut level: Vec<DoubleSha256> = indexed.into_iter().map(|(_, h)| h).collect();
+    let mut position = leaf_index;
+    let mut siblings = Vec::new();
+
+    while level.len() > 1 {
+        if level.len() % 2 == 1 {
+            let last = level.last().unwrap().clone();
+            level.push(last);
+        }
+        siblings.push(level[position ^ 1].clone());
+        position /= 2;
+        level = level.chunks(2).map(|pair| hash_node(&pair[0], &pair[1])).collect();
+    }
+
+    Ok(Some(OpMerkleProof { leaf_index: leaf_index as u64, siblings }))
+}
+
+/// Verifies that `leaf` is included under `root` via `proof`, climbing from the leaf upward: at
+/// each level, combine with the sibling on whichever side the current index's low bit
+/// indicates, then shift the index down a level -- the same walk `verify_tx_merkle_path` and
+/// `accumulator::verify_and_prune` use for their own trees.
+pub fn verify_op_merkle_proof(leaf: &DoubleSha256, proof: &OpMerkleProof, root: &DoubleSha256) -> bool {
+    let mut cur = leaf.clone();
+    let mut index = proof.leaf_index;
+
+    for sibling in proof.siblings.iter() {
+        cur = if index & 1 == 0 { hash_node(&cur, sibling) } else { hash_node(sibling, &cur) };
+        index >>= 1;
+    }
+
+    &cur == root
+}
+
+/// Verifies that `op` belongs under `root` via `proof` -- the single-call path `check()` (or a
+/// light client standing in for it) can use to optionally assert an op's membership, on top of
+/// whatever `check()` itself already verifies.
+pub fn check_op_inclusion(op: &BlockstackOperationType, proof: &OpMerkleProof, root: &DoubleSha256) -> Result<(), op_error> {
+    let leaf = op_leaf_hash(op)?;
+    if verify_op_merkle_proof(&leaf, proof, root) {
+        Ok(())
+    } else {
+        Err(op_error::InvalidInput)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chainstate::burn::{BlockHeaderHash, ConsensusHash, VRFSeed};
+    use chainstate::stacks::StacksAddress;
+    use burnchains::{Address, BurnchainHeaderHash, BurnchainSigner, Txid};
+    use burnchains::bitcoin::address::BitcoinAddress;
+    use burnchains::bitcoin::BitcoinNetworkType;
+    use address::AddressHashMode;
+    use util::vrf::VRFPublicKey;
+    use util::hash::hex_bytes;
+
+    use chainstate::burn::operations::{LeaderBlockCommitOp, LeaderKeyRegisterOp};
+
+    fn sample_commit(vtxindex: u32) -> BlockstackOperationType {
+        BlockstackOperationType::LeaderBlockCommit(LeaderBlockCommitOp {
+            block_header_hash: BlockHeaderHash::from_bytes(&[vtxindex as u8; 32]).unwrap(),
+            new_seed: VRFSeed::from_bytes(&[0u8; 32]).unwrap(),
+            parent_block_backptr: 0,
+            parent_vtxindex: 0,
+            key_block_backptr: 0,
+            key_vtxindex: 0,
+            epoch_num: 0,
+            memo: vec![1, 2, 3],
+            burn_fee: 10_000,
+            input: BurnchainSigner {
+                hash_mode: AddressHashMode::SerializeP2PKH,
+                num_sigs: 1,
+                public_keys: vec![],
+            },
+            txid: Txid::from_hex("1bfa831b5fc56c858198acb8e77e5863c1e9d8ac26d49ddb914e24d8d4083562").unwrap(),
+            vtxindex: vtxindex,
+            block_height: 100,
+            burn_header_hash: BurnchainHeaderHash::from_hex("0000000000000000000000000000000000000000000000000000000000000003").unwrap(),
+            fork_segment_id: 0,
+            pow_nonce: None,
+            treasury_fee: 0,
+            treasury_address: None,
+        })
+    }
+
+    fn sample_key_register(vtxindex: u32) -> BlockstackOperationType {
+        BlockstackOperationType::LeaderKeyRegister(LeaderKeyRegisterOp {
+            consensus_hash: ConsensusHash::from_bytes(&hex_bytes("0000000000000000000000000000000000000000").unwrap()).unwrap(),
+            public_key: VRFPublicKey::from_bytes(&hex_bytes("a366b51292bef4edd64063d9145c617fec373bceb0758e98cd72becd84d54c7a").unwrap()).unwrap(),
+            memo: vec![1, 2, 3],
+            address: StacksAddress::from_bitcoin_address(&BitcoinAddress::from_scriptpubkey(BitcoinNetworkType::Testnet, &hex_bytes("76a9140be3e286a15ea85882761618e366586b5574100d88ac").unwrap()).unwrap()),
+            txid: Txid::from_hex("1bfa831b5fc56c858198acb8e77e5863c1e9d8ac26d49ddb914e24d8d4083562").unwrap(),
+            vtxindex: vtxindex,
+            block_height: 100,
+            burn_header_hash: BurnchainHeaderHash::from_hex("0000000000000000000000000000000000000000000000000000000000000003").unwrap(),
+            fork_segment_id: 0,
+        })
+    }
+
+    #[test]
+    fn single_op_root_is_just_its_own_leaf_hash() {
+        let op = sample_commit(0);
+        let root = build_op_merkle_root(&[op.clone()]).unwrap();
+        assert_eq!(root, op_leaf_hash(&op).unwrap());
+    }
+
+    #[test]
+    fn root_is_independent_of_input_order() {
+        let ops_in_order = vec![sample_commit(0), sample_key_register(1), sample_commit(2)];
+        let ops_reversed = vec![sample_commit(2), sample_key_register(1), sample_commit(0)];
+
+        assert_eq!(build_op_merkle_root(&ops_in_order).unwrap(), build_op_merkle_root(&ops_reversed).unwrap());
+    }
+
+    #[test]
+    fn empty_op_set_has_no_root() {
+        assert_eq!(build_op_merkle_root(&[]), Err(op_error::ParseError));
+    }
+
+    #[test]
+    fn builds_and_verifies_a_proof_at_every_position_of_an_odd_sized_tree() {
+        let ops = vec![sample_commit(0), sample_key_register(1), sample_commit(2)];
+        let root = build_op_merkle_root(&ops).unwrap();
+
+        for vtxindex in 0..3u32 {
+            let proof = build_op_merkle_proof(&ops, vtxindex).unwrap().unwrap();
+            let op = ops.iter().find(|op| op_vtxindex(op).unwrap() == vtxindex).unwrap();
+            assert!(check_op_inclusion(op, &proof, &root).is_ok());
+        }
+    }
+
+    #[test]
+    fn rejects_a_proof_against_the_wrong_op() {
+        let ops = vec![sample_commit(0), sample_key_register(1)];
+        let root = build_op_merkle_root(&ops).unwrap();
+        let proof = build_op_merkle_proof(&ops, 0).unwrap().unwrap();
+
+        assert_eq!(check_op_inclusion(&sample_commit(99), &proof, &root), Err(op_error::InvalidInput));
+    }
+
+    #[test]
+    fn proof_lookup_for_a_missing_vtxindex_is_none() {
+        let ops = vec![sample_commit(0)];
+        assert_eq!(build_op_merkle_proof(&ops, 42).unwrap(), None);
+    }
+}
@@ -0,0 +1,124 @@
+/*
+ copyright: (c) 2013-2018 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! An orphan pool for `LeaderBlockCommitOp`s whose parent commit or leader key hasn't been
+//! seen yet in the chain tip's fork segment. Reorgs and out-of-order fork delivery mean a
+//! commit can be well-formed and eventually valid, but still fail `check()` today simply
+//! because the sibling fork segment it depends on hasn't been materialized in `BurnDB` yet.
+//! Rather than permanently dropping such a commit, `check_or_orphan` stashes it keyed by the
+//! (height, vtxindex, fork segment) of the dependency it's missing; `resolve_orphans` is the
+//! other half, re-running `check()` on every orphan that a newly-accepted commit or leader
+//! key satisfies, cascading to whatever that unblocks in turn.
+
+use chainstate::burn::operations::Error as op_error;
+use chainstate::burn::operations::{BlockstackOperation, LeaderBlockCommitOp};
+use chainstate::burn::operations::epoch::EpochList;
+
+use chainstate::burn::db::burndb::BurnDB;
+
+use burnchains::Burnchain;
+use burnchains::BurnchainBlockHeader;
+
+use util::db::DBTx;
+use util::log;
+
+/// Orphans older than this many blocks are evicted by `evict_stale_orphans`, regardless of
+/// whether the dependency they're waiting on ever shows up -- otherwise a commit that
+/// references a fork segment that never gets built out would sit in the pool forever.
+pub const ORPHAN_MAX_AGE_BLOCKS: u64 = 144;
+
+/// What became of a commit passed through `check_or_orphan`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum OrphanCheckResult {
+    /// The commit passed `check()` outright.
+    Accepted,
+    /// The commit's parent or leader key isn't present yet; it's been stashed in the orphan
+    /// pool and will be retried by `resolve_orphans` once that dependency shows up.
+    Orphaned,
+}
+
+/// Runs `op.check()`, and if it fails only because its parent commit or leader key isn't
+/// present yet in `chain_tip`'s fork segment, stashes it in the orphan pool instead of
+/// propagating the error. Any other `check()` failure is returned as-is -- an orphan slot is
+/// for a commit that's valid modulo timing, not one that's simply malformed.
+pub fn check_or_orphan<'a>(op: &LeaderBlockCommitOp, burnchain: &Burnchain, block_header: &BurnchainBlockHeader, epochs: &EpochList, tx: &mut DBTx<'a>) -> Result<OrphanCheckResult, op_error> {
+    match op.check(burnchain, block_header, epochs, tx) {
+        Ok(()) => Ok(OrphanCheckResult::Accepted),
+
+        Err(op_error::BlockCommitNoParent) => {
+            let missing_height = op.block_height - (op.parent_block_backptr as u64);
+            BurnDB::insert_orphan_commit(tx, op, missing_height, op.parent_vtxindex.into(), block_header.fork_segment_id, block_header.block_height)?;
+            warn!("Orphaned block commit {},{}: awaiting parent at ({},{}) in fork {}", op.block_height, op.vtxindex, missing_height, op.parent_vtxindex, block_header.fork_segment_id);
+            Ok(OrphanCheckResult::Orphaned)
+        },
+
+        Err(op_error::BlockCommitNoLeaderKey) => {
+            let missing_height = op.block_height - (op.key_block_backptr as u64);
+            BurnDB::insert_orphan_commit(tx, op, missing_height, op.key_vtxindex.into(), block_header.fork_segment_id, block_header.block_height)?;
+            warn!("Orphaned block commit {},{}: awaiting leader key at ({},{}) in fork {}", op.block_height, op.vtxindex, missing_height, op.key_vtxindex, block_header.fork_segment_id);
+            Ok(OrphanCheckResult::Orphaned)
+        },
+
+        Err(e) => Err(e),
+    }
+}
+
+/// Re-runs `check()` on every orphan waiting on the dependency at `(satisfied_height,
+/// satisfied_vtxindex)` in `fork_segment_id` -- call this once a block commit or leader key at
+/// that position has just been accepted. An orphan that's still missing something is
+/// re-orphaned (it may now be waiting on a different dependency); an orphan that now passes is
+/// accepted, and in turn unblocks whatever was waiting on *it*, so this cascades until nothing
+/// new resolves.
+pub fn resolve_orphans<'a>(satisfied_height: u64, satisfied_vtxindex: u32, fork_segment_id: u64, burnchain: &Burnchain, block_header: &BurnchainBlockHeader, epochs: &EpochList, tx: &mut DBTx<'a>) -> Result<Vec<LeaderBlockCommitOp>, op_error> {
+    let mut accepted = Vec::new();
+    let mut frontier = vec![(satisfied_height, satisfied_vtxindex)];
+
+    while let Some((height, vtxindex)) = frontier.pop() {
+        let waiting = BurnDB::get_orphans_waiting_on(tx, height, vtxindex, fork_segment_id)?;
+
+        for orphan in waiting {
+            BurnDB::remove_orphan_commit(tx, &orphan, fork_segment_id)?;
+
+            match check_or_orphan(&orphan, burnchain, block_header, epochs, tx)? {
+                OrphanCheckResult::Accepted => {
+                    frontier.push((orphan.block_height, orphan.vtxindex));
+                    accepted.push(orphan);
+                },
+                OrphanCheckResult::Orphaned => {
+                    // re-stashed under whatever it's missing now; nothing further to do here
+                },
+            }
+        }
+    }
+
+    Ok(accepted)
+}
+
+/// Evicts every orphan queued more than `ORPHAN_MAX_AGE_BLOCKS` before `current_height`,
+/// keeping the pool bounded when a commit's dependency never materializes (e.g. it referenced
+/// a fork segment that got abandoned). Returns the number of orphans evicted.
+pub fn evict_stale_orphans<'a>(current_height: u64, tx: &mut DBTx<'a>) -> Result<u64, op_error> {
+    let cutoff_height = current_height.saturating_sub(ORPHAN_MAX_AGE_BLOCKS);
+    BurnDB::evict_orphan_commits_older_than(tx, cutoff_height)
+}
+
+/// The current size of the orphan pool, for exposing as a node metric.
+pub fn orphan_pool_size<'a>(tx: &mut DBTx<'a>) -> Result<u64, op_error> {
+    BurnDB::count_orphan_commits(tx)
+}
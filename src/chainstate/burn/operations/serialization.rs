@@ -0,0 +1,114 @@
+/*
+ copyright: (c) 2013-2018 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! A versioned, self-describing wire format for burn-op payloads, independent of the
+//! fixed-offset OP_RETURN layouts parsed directly out of burnchain transactions today. Each
+//! encoded payload is prefixed with a single wire-version byte, so a future field addition
+//! (or a wholly different encoding) can be introduced without colliding with what a given
+//! client build already knows how to read -- an unrecognized version is reported rather than
+//! misparsed.
+
+use chainstate::burn::operations::Error as op_error;
+
+/// Highest burn-op wire version this build knows how to decode.
+pub const CURRENT_WIRE_VERSION: u8 = 1;
+
+/// Implemented by burn-op payloads (or payload fragments) that can be written out in the
+/// versioned wire format.
+pub trait Serializable {
+    fn serialize(&self) -> Vec<u8>;
+}
+
+/// Implemented by burn-op payloads that can be read back out of the versioned wire format.
+pub trait Deserializable: Sized {
+    fn deserialize(version: u8, bytes: &[u8]) -> Result<Self, op_error>;
+}
+
+/// Prepends the given wire version to `payload`, producing a self-describing byte string.
+pub fn encode_versioned(version: u8, payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(1 + payload.len());
+    out.push(version);
+    out.extend_from_slice(payload);
+    out
+}
+
+/// Splits a self-describing byte string into its wire version and payload, failing if the
+/// version is higher than anything this build understands how to decode.
+pub fn decode_versioned(data: &[u8]) -> Result<(u8, &[u8]), op_error> {
+    let (version, payload) = data.split_first()
+        .ok_or(op_error::ParseError)?;
+
+    if *version > CURRENT_WIRE_VERSION {
+        return Err(op_error::ParseError);
+    }
+
+    Ok((*version, payload))
+}
+
+/// Encodes `item` at the current wire version.
+pub fn to_versioned_bytes<T: Serializable>(item: &T) -> Vec<u8> {
+    encode_versioned(CURRENT_WIRE_VERSION, &item.serialize())
+}
+
+/// Decodes an item previously produced by `to_versioned_bytes`.
+pub fn from_versioned_bytes<T: Deserializable>(data: &[u8]) -> Result<T, op_error> {
+    let (version, payload) = decode_versioned(data)?;
+    T::deserialize(version, payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Echo(Vec<u8>);
+
+    impl Serializable for Echo {
+        fn serialize(&self) -> Vec<u8> {
+            self.0.clone()
+        }
+    }
+
+    impl Deserializable for Echo {
+        fn deserialize(_version: u8, bytes: &[u8]) -> Result<Echo, op_error> {
+            Ok(Echo(bytes.to_vec()))
+        }
+    }
+
+    #[test]
+    fn roundtrips_through_the_versioned_envelope() {
+        let item = Echo(vec![1, 2, 3, 4]);
+        let encoded = to_versioned_bytes(&item);
+        assert_eq!(encoded[0], CURRENT_WIRE_VERSION);
+
+        let decoded: Echo = from_versioned_bytes(&encoded).unwrap();
+        assert_eq!(decoded.0, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn rejects_a_version_newer_than_this_build_understands() {
+        let data = encode_versioned(CURRENT_WIRE_VERSION + 1, &[0xaa]);
+        let result: Result<Echo, op_error> = from_versioned_bytes(&data);
+        assert_eq!(result.err(), Some(op_error::ParseError));
+    }
+
+    #[test]
+    fn rejects_empty_input() {
+        assert_eq!(decode_versioned(&[]).err(), Some(op_error::ParseError));
+    }
+}
@@ -0,0 +1,72 @@
+/*
+ copyright: (c) 2013-2018 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Light-client entry point for parsing and checking a single burn op: a caller supplies one
+//! `BurnchainTransaction`, its Merkle inclusion proof against the underlying Bitcoin block's
+//! Merkle root (see `burnchains::bitcoin::merkle`), and the `BurnchainBlockHeader` it claims to
+//! belong to. This is enough to run the same `from_tx` + `check()` path a fully-synced node
+//! runs, without that caller ever having downloaded or parsed the rest of the block.
+
+use chainstate::burn::Opcodes;
+use chainstate::burn::operations::Error as op_error;
+use chainstate::burn::operations::{
+    BlockstackOperation,
+    BlockstackOperationType,
+    LeaderBlockCommitOp,
+    LeaderKeyRegisterOp,
+};
+use chainstate::burn::operations::epoch::EpochList;
+
+use burnchains::Burnchain;
+use burnchains::BurnchainBlockHeader;
+use burnchains::BurnchainTransaction;
+use burnchains::Txid;
+use burnchains::bitcoin::blocks::BitcoinBlockParser;
+
+use util::db::DBTx;
+use util::log;
+
+/// Verifies that `txid` was included at `tx_index` under `merkle_root` via `merkle_branch`,
+/// then parses and checks `burnchain_tx` as a Blockstack op against `block_header`. The
+/// inclusion proof covers `txid` only; this function does not check that `burnchain_tx`
+/// hashes to `txid`. The op is rejected without ever touching `BurnDB` if the inclusion proof alone doesn't check
+/// out -- a light client shouldn't burn a DB lookup on a tx it can't even prove was mined.
+pub fn parse_and_check<'a>(txid: &Txid, tx_index: u32, merkle_branch: &[Txid], merkle_root: &Txid, burnchain_tx: &BurnchainTransaction, burnchain: &Burnchain, block_header: &BurnchainBlockHeader, epochs: &EpochList, tx: &mut DBTx<'a>) -> Result<BlockstackOperationType, op_error> {
+    if !BitcoinBlockParser::verify_tx_merkle_path(txid, tx_index, merkle_branch, merkle_root) {
+        warn!("SPV proof failed: tx {} is not included at index {} under merkle root {}", txid.to_hex(), tx_index, merkle_root.to_hex());
+        return Err(op_error::ParseError);
+    }
+
+    let opcode = burnchain_tx.opcode();
+
+    if opcode == Opcodes::LeaderKeyRegister as u8 {
+        let parsed = LeaderKeyRegisterOp::from_tx(block_header, burnchain_tx)?;
+        parsed.check(burnchain, block_header, epochs, tx)?;
+        return Ok(BlockstackOperationType::LeaderKeyRegister(parsed));
+    }
+
+    if opcode == Opcodes::LeaderBlockCommit as u8 {
+        let parsed = LeaderBlockCommitOp::from_tx(block_header, burnchain_tx)?;
+        parsed.check(burnchain, block_header, epochs, tx)?;
+        return Ok(BlockstackOperationType::LeaderBlockCommit(parsed));
+    }
+
+    warn!("SPV parse failed: unrecognized opcode {}", opcode);
+    Err(op_error::ParseError)
+}
@@ -0,0 +1,83 @@
+/*
+ copyright: (c) 2013-2018 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Glue between newly-accepted ops and `BurnDB`'s watch-list (`install_watch_signer`,
+//! `install_watch_address`, `is_address_watched`, `insert_watched_op`, `get_watched_ops_since`):
+//! a wallet subscribes to a `BurnchainSigner` it signs with, or to a `StacksAddress` directly,
+//! and every block commit or leader key registration whose derived input address matches gets
+//! recorded so it can be enumerated later without rescanning every snapshot.
+//!
+//! A `BurnchainSigner` and the `StacksAddress` it controls already resolve to the same byte
+//! representation -- `BurnchainSigner::to_address_bits()` produces exactly what
+//! `StacksAddress::to_bytes()` does, which is how `LeaderBlockCommitOp::check` is able to
+//! compare a commit's input directly against a leader key's registered address. That means
+//! `install_watch_signer` and `install_watch_address` can (and do) key the same watch-list
+//! table, regardless of which form a caller happened to have on hand.
+
+use chainstate::burn::operations::Error as op_error;
+use chainstate::burn::operations::{BlockstackOperationType, LeaderBlockCommitOp, LeaderKeyRegisterOp};
+
+use chainstate::burn::db::burndb::BurnDB;
+
+use burnchains::{Address, BurnchainSigner};
+
+use chainstate::stacks::StacksAddress;
+
+use util::db::DBTx;
+
+/// Subscribes a signer's derived address to the watch list.
+pub fn install_watch_signer<'a>(tx: &mut DBTx<'a>, signer: &BurnchainSigner) -> Result<(), op_error> {
+    BurnDB::install_watch_signer(tx, signer)
+}
+
+/// Subscribes an address directly to the watch list, e.g. for a wallet that holds the address
+/// but not (yet) a commit or key register signed by it.
+pub fn install_watch_address<'a>(tx: &mut DBTx<'a>, address: &StacksAddress) -> Result<(), op_error> {
+    BurnDB::install_watch_address(tx, address)
+}
+
+/// Records `op` against its input signer's derived address if that address is on the watch
+/// list. Run this wherever `BurnDB::insert_block_commit` is called to accept the op.
+pub fn record_block_commit_if_watched<'a>(tx: &mut DBTx<'a>, op: &LeaderBlockCommitOp) -> Result<(), op_error> {
+    let address_bits = op.input.to_address_bits();
+
+    if BurnDB::is_address_watched(tx, &address_bits)? {
+        BurnDB::insert_watched_op(tx, &address_bits, &BlockstackOperationType::LeaderBlockCommit(op.clone()), op.block_height)?;
+    }
+
+    Ok(())
+}
+
+/// Records `op` against its registering address if that address is on the watch list. Run
+/// this wherever `BurnDB::insert_leader_key` is called to accept the op.
+pub fn record_leader_key_if_watched<'a>(tx: &mut DBTx<'a>, op: &LeaderKeyRegisterOp) -> Result<(), op_error> {
+    let address_bits = op.address.to_bytes();
+
+    if BurnDB::is_address_watched(tx, &address_bits)? {
+        BurnDB::insert_watched_op(tx, &address_bits, &BlockstackOperationType::LeaderKeyRegister(op.clone()), op.block_height)?;
+    }
+
+    Ok(())
+}
+
+/// Enumerates every watched op recorded for `address` at or after `block_height`, so a wallet
+/// can pick up exactly where its last scan left off.
+pub fn get_watched_ops_since<'a>(tx: &mut DBTx<'a>, address: &StacksAddress, block_height: u64) -> Result<Vec<BlockstackOperationType>, op_error> {
+    BurnDB::get_watched_ops_since(tx, address, block_height)
+}
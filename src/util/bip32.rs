@@ -0,0 +1,225 @@
+/*
+ copyright: (c) 2013-2018 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! The standard BIP32 hierarchical-deterministic key recurrence, independent of anything
+//! burnchain-specific: a 32-byte seed becomes a master extended private key via
+//! HMAC-SHA512("Bitcoin seed", seed), and each child is derived from its parent's chain code and
+//! (for a hardened child) private key or (for a normal child) public key. `burnchains::hd`
+//! builds the burn-op-specific key shapes (a `BurnchainSigner`, a VRF prover key) on top of this.
+
+use deps::secp256k1::{Secp256k1, SecretKey, PublicKey};
+use util::hash::hmac_sha512;
+
+/// BIP32 reserves the top bit of the 32-bit index to mark a hardened child -- one derived from
+/// the parent's private key rather than its public key, so it can't be derived by anyone who
+/// only has the parent's public key and chain code.
+const HARDENED_BIT: u32 = 1 << 31;
+
+/// One step of a derivation path: either a normal child (derivable from a public key alone) or
+/// a hardened child (derivable only from a private key).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChildNumber {
+    Normal(u32),
+    Hardened(u32),
+}
+
+impl ChildNumber {
+    fn to_index(&self) -> u32 {
+        match *self {
+            ChildNumber::Normal(i) => i,
+            ChildNumber::Hardened(i) => i | HARDENED_BIT,
+        }
+    }
+
+    fn is_hardened(&self) -> bool {
+        match *self {
+            ChildNumber::Normal(_) => false,
+            ChildNumber::Hardened(_) => true,
+        }
+    }
+}
+
+/// A sequence of child numbers to derive from a master key, e.g. `m/purpose'/fork_segment_id'/epoch_num`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DerivationPath(pub Vec<ChildNumber>);
+
+impl DerivationPath {
+    pub fn new(steps: Vec<ChildNumber>) -> DerivationPath {
+        DerivationPath(steps)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Bip32Error {
+    /// BIP32 requires at least a 128-bit seed; this implementation additionally requires the
+    /// seed be exactly 32 bytes, since that's all `burnchains::hd` ever feeds it. Also returned
+    /// when a master or child scalar derived from the HMAC output is not a valid private key.
+    InvalidSeedLength,
+    /// A normal (non-hardened) child was requested from a key this implementation doesn't have
+    /// the public key material for. `derive_path` also returns it when every remaining index at
+    /// one step fails to derive a child, i.e. that depth's index space is exhausted.
+    MissingPublicKey,
+}
+
+/// An extended private key: a private key plus the chain code needed to derive its children.
+#[derive(Clone)]
+pub struct ExtendedPrivateKey {
+    pub private_key: SecretKey,
+    pub chain_code: [u8; 32],
+    pub depth: u8,
+    pub child_number: u32,
+}
+
+impl ExtendedPrivateKey {
+    /// Derives the master extended private key from a raw seed, per BIP32: split
+    /// HMAC-SHA512("Bitcoin seed", seed) into its left and right 32 bytes, the former becoming
+    /// the master private key and the latter its chain code.
+    pub fn master(seed: &[u8]) -> Result<ExtendedPrivateKey, Bip32Error> {
+        if seed.len() != 32 {
+            return Err(Bip32Error::InvalidSeedLength);
+        }
+
+        let i = hmac_sha512(b"Bitcoin seed", seed);
+        let (il, ir) = i.split_at(32);
+
+        let secp = Secp256k1::new();
+        let private_key = SecretKey::from_slice(&secp, il).map_err(|_| Bip32Error::InvalidSeedLength)?;
+
+        let mut chain_code = [0u8; 32];
+        chain_code.copy_from_slice(ir);
+
+        Ok(ExtendedPrivateKey { private_key, chain_code, depth: 0, child_number: 0 })
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        let secp = Secp256k1::new();
+        PublicKey::from_secret_key(&secp, &self.private_key)
+    }
+
+    /// Derives a single child key. Per BIP32, a hardened child is derived from
+    /// `0x00 || ser256(parent private key) || index`, a normal child from
+    /// `serP(parent public key) || index`; the resulting HMAC-SHA512 splits into a 32-byte
+    /// scalar added to the parent's private key (mod the curve order) and a 32-byte chain code.
+    /// In the vanishingly unlikely case the scalar is out of range or the tweak yields the
+    /// identity, BIP32 has the deriver move on to the next index -- exactly what the `Err` ->
+    /// retry loop in `derive_path` below does, so this method itself just reports the failure.
+    pub fn derive_child(&self, child: ChildNumber) -> Result<ExtendedPrivateKey, Bip32Error> {
+        let index = child.to_index();
+        let secp = Secp256k1::new();
+
+        let mut data = Vec::with_capacity(37);
+        if child.is_hardened() {
+            data.push(0u8);
+            data.extend_from_slice(&self.private_key[..]);
+        } else {
+            data.extend_from_slice(&self.public_key().serialize());
+        }
+        data.extend_from_slice(&index.to_be_bytes());
+
+        let i = hmac_sha512(&self.chain_code, &data);
+        let (il, ir) = i.split_at(32);
+
+        let mut child_key = self.private_key.clone();
+        child_key.add_assign(&secp, il).map_err(|_| Bip32Error::InvalidSeedLength)?;
+
+        let mut chain_code = [0u8; 32];
+        chain_code.copy_from_slice(ir);
+
+        Ok(ExtendedPrivateKey {
+            private_key: child_key,
+            chain_code,
+            depth: self.depth + 1,
+            child_number: index,
+        })
+    }
+
+    /// Walks a full `DerivationPath` from this key, skipping to the next index at any step
+    /// whose child derivation fails (the out-of-range-scalar/identity-point case BIP32 defers
+    /// to the deriver) rather than propagating that as an error the caller has to handle --
+    /// the request's "skip to the next index" behavior.
+    pub fn derive_path(&self, path: &DerivationPath) -> Result<ExtendedPrivateKey, Bip32Error> {
+        let mut key = self.clone();
+        for step in path.0.iter() {
+            key = key.derive_child_skipping_invalid(*step)?;
+        }
+        Ok(key)
+    }
+
+    fn derive_child_skipping_invalid(&self, child: ChildNumber) -> Result<ExtendedPrivateKey, Bip32Error> {
+        let mut index = child.to_index() & !HARDENED_BIT;
+        let hardened = child.is_hardened();
+
+        loop {
+            let candidate = if hardened { ChildNumber::Hardened(index) } else { ChildNumber::Normal(index) };
+            match self.derive_child(candidate) {
+                Ok(key) => return Ok(key),
+                Err(_) => {
+                    index += 1;
+                    if index & HARDENED_BIT != 0 {
+                        // exhausted the index space for this depth -- not reachable in practice
+                        return Err(Bip32Error::MissingPublicKey);
+                    }
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::hash::hex_bytes;
+
+    fn seed() -> Vec<u8> {
+        hex_bytes("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f").unwrap()
+    }
+
+    #[test]
+    fn master_key_requires_a_32_byte_seed() {
+        assert_eq!(ExtendedPrivateKey::master(&[0u8; 16]), Err(Bip32Error::InvalidSeedLength));
+        assert!(ExtendedPrivateKey::master(&seed()).is_ok());
+    }
+
+    #[test]
+    fn deriving_the_same_path_twice_is_deterministic() {
+        let master = ExtendedPrivateKey::master(&seed()).unwrap();
+        let path = DerivationPath::new(vec![ChildNumber::Hardened(5183), ChildNumber::Hardened(2), ChildNumber::Normal(7)]);
+
+        let a = master.derive_path(&path).unwrap();
+        let b = master.derive_path(&path).unwrap();
+        assert_eq!(a.private_key[..], b.private_key[..]);
+        assert_eq!(a.chain_code, b.chain_code);
+    }
+
+    #[test]
+    fn hardened_and_normal_children_at_the_same_index_differ() {
+        let master = ExtendedPrivateKey::master(&seed()).unwrap();
+        let hardened = master.derive_child(ChildNumber::Hardened(0)).unwrap();
+        let normal = master.derive_child(ChildNumber::Normal(0)).unwrap();
+        assert_ne!(hardened.private_key[..], normal.private_key[..]);
+    }
+
+    #[test]
+    fn different_paths_yield_different_keys() {
+        let master = ExtendedPrivateKey::master(&seed()).unwrap();
+        let a = master.derive_path(&DerivationPath::new(vec![ChildNumber::Hardened(5183), ChildNumber::Hardened(0), ChildNumber::Normal(0)])).unwrap();
+        let b = master.derive_path(&DerivationPath::new(vec![ChildNumber::Hardened(5183), ChildNumber::Hardened(1), ChildNumber::Normal(0)])).unwrap();
+        assert_ne!(a.private_key[..], b.private_key[..]);
+    }
+}
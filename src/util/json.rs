@@ -0,0 +1,273 @@
+/*
+ copyright: (c) 2013-2018 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! A minimal, dependency-free JSON reader. This exists solely to load the versioned test-vector
+//! files under `chainstate::burn::operations::fixtures` -- it is not a general-purpose JSON
+//! library, so it keeps to the handful of things a fixture file needs: objects, arrays, strings,
+//! numbers, bools, and null, with object keys preserved in file order rather than hashed, since
+//! fixture files are meant to be read (and diffed) by a person as much as by this loader.
+
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    pub fn parse(input: &str) -> Result<JsonValue, JsonError> {
+        let chars: Vec<char> = input.chars().collect();
+        let mut pos = 0;
+        let value = parse_value(&chars, &mut pos)?;
+        skip_whitespace(&chars, &mut pos);
+        if pos != chars.len() {
+            return Err(JsonError(format!("trailing data at position {}", pos)));
+        }
+        Ok(value)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(s) => Some(s.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<JsonValue>> {
+        match self {
+            JsonValue::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            JsonValue::Number(n) if *n >= 0.0 => Some(*n as u64),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        *self == JsonValue::Null
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonError(pub String);
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "JSON parse error: {}", self.0)
+    }
+}
+
+fn skip_whitespace(chars: &[char], pos: &mut usize) {
+    while *pos < chars.len() && chars[*pos].is_whitespace() {
+        *pos += 1;
+    }
+}
+
+fn expect(chars: &[char], pos: &mut usize, c: char) -> Result<(), JsonError> {
+    if *pos >= chars.len() || chars[*pos] != c {
+        return Err(JsonError(format!("expected '{}' at position {}", c, pos)));
+    }
+    *pos += 1;
+    Ok(())
+}
+
+fn parse_value(chars: &[char], pos: &mut usize) -> Result<JsonValue, JsonError> {
+    skip_whitespace(chars, pos);
+    if *pos >= chars.len() {
+        return Err(JsonError("unexpected end of input".to_string()));
+    }
+
+    match chars[*pos] {
+        '{' => parse_object(chars, pos),
+        '[' => parse_array(chars, pos),
+        '"' => Ok(JsonValue::String(parse_string(chars, pos)?)),
+        't' => parse_literal(chars, pos, "true", JsonValue::Bool(true)),
+        'f' => parse_literal(chars, pos, "false", JsonValue::Bool(false)),
+        'n' => parse_literal(chars, pos, "null", JsonValue::Null),
+        '-' | '0'..='9' => parse_number(chars, pos),
+        c => Err(JsonError(format!("unexpected character '{}' at position {}", c, pos))),
+    }
+}
+
+fn parse_literal(chars: &[char], pos: &mut usize, literal: &str, value: JsonValue) -> Result<JsonValue, JsonError> {
+    let end = *pos + literal.len();
+    if end > chars.len() || chars[*pos..end].iter().collect::<String>() != literal {
+        return Err(JsonError(format!("expected '{}' at position {}", literal, pos)));
+    }
+    *pos = end;
+    Ok(value)
+}
+
+fn parse_object(chars: &[char], pos: &mut usize) -> Result<JsonValue, JsonError> {
+    expect(chars, pos, '{')?;
+    let mut entries = Vec::new();
+    skip_whitespace(chars, pos);
+    if *pos < chars.len() && chars[*pos] == '}' {
+        *pos += 1;
+        return Ok(JsonValue::Object(entries));
+    }
+
+    loop {
+        skip_whitespace(chars, pos);
+        let key = parse_string(chars, pos)?;
+        skip_whitespace(chars, pos);
+        expect(chars, pos, ':')?;
+        let value = parse_value(chars, pos)?;
+        entries.push((key, value));
+
+        skip_whitespace(chars, pos);
+        if *pos < chars.len() && chars[*pos] == ',' {
+            *pos += 1;
+            continue;
+        }
+        break;
+    }
+
+    skip_whitespace(chars, pos);
+    expect(chars, pos, '}')?;
+    Ok(JsonValue::Object(entries))
+}
+
+fn parse_array(chars: &[char], pos: &mut usize) -> Result<JsonValue, JsonError> {
+    expect(chars, pos, '[')?;
+    let mut items = Vec::new();
+    skip_whitespace(chars, pos);
+    if *pos < chars.len() && chars[*pos] == ']' {
+        *pos += 1;
+        return Ok(JsonValue::Array(items));
+    }
+
+    loop {
+        items.push(parse_value(chars, pos)?);
+        skip_whitespace(chars, pos);
+        if *pos < chars.len() && chars[*pos] == ',' {
+            *pos += 1;
+            continue;
+        }
+        break;
+    }
+
+    skip_whitespace(chars, pos);
+    expect(chars, pos, ']')?;
+    Ok(JsonValue::Array(items))
+}
+
+fn parse_string(chars: &[char], pos: &mut usize) -> Result<String, JsonError> {
+    expect(chars, pos, '"')?;
+    let mut out = String::new();
+    loop {
+        if *pos >= chars.len() {
+            return Err(JsonError("unterminated string".to_string()));
+        }
+        let c = chars[*pos];
+        *pos += 1;
+        match c {
+            '"' => break,
+            '\\' => {
+                if *pos >= chars.len() {
+                    return Err(JsonError("unterminated escape sequence".to_string()));
+                }
+                let escaped = chars[*pos];
+                *pos += 1;
+                match escaped {
+                    '"' => out.push('"'),
+                    '\\' => out.push('\\'),
+                    '/' => out.push('/'),
+                    'n' => out.push('\n'),
+                    't' => out.push('\t'),
+                    'r' => out.push('\r'),
+                    'b' => out.push('\u{0008}'),
+                    'f' => out.push('\u{000C}'),
+                    'u' => {
+                        if *pos + 4 > chars.len() {
+                            return Err(JsonError("truncated unicode escape".to_string()));
+                        }
+                        let hex: String = chars[*pos..*pos + 4].iter().collect();
+                        let code = u32::from_str_radix(&hex, 16)
+                            .map_err(|_e| JsonError(format!("invalid unicode escape '{}'", hex)))?;
+                        out.push(std::char::from_u32(code).unwrap_or('\u{FFFD}'));
+                        *pos += 4;
+                    },
+                    other => return Err(JsonError(format!("invalid escape '\\{}'", other))),
+                }
+            },
+            other => out.push(other),
+        }
+    }
+    Ok(out)
+}
+
+fn parse_number(chars: &[char], pos: &mut usize) -> Result<JsonValue, JsonError> {
+    let start = *pos;
+    if *pos < chars.len() && chars[*pos] == '-' {
+        *pos += 1;
+    }
+    while *pos < chars.len() && (chars[*pos].is_ascii_digit() || chars[*pos] == '.' || chars[*pos] == 'e' || chars[*pos] == 'E' || chars[*pos] == '+' || chars[*pos] == '-') {
+        *pos += 1;
+    }
+    let text: String = chars[start..*pos].iter().collect();
+    text.parse::<f64>()
+        .map(JsonValue::Number)
+        .map_err(|_e| JsonError(format!("invalid number literal '{}'", text)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_scalars() {
+        assert_eq!(JsonValue::parse("null").unwrap(), JsonValue::Null);
+        assert_eq!(JsonValue::parse("true").unwrap(), JsonValue::Bool(true));
+        assert_eq!(JsonValue::parse("false").unwrap(), JsonValue::Bool(false));
+        assert_eq!(JsonValue::parse("42").unwrap(), JsonValue::Number(42.0));
+        assert_eq!(JsonValue::parse("\"hi\"").unwrap(), JsonValue::String("hi".to_string()));
+    }
+
+    #[test]
+    fn parses_nested_objects_and_arrays() {
+        let parsed = JsonValue::parse(r#"{"a": [1, 2, {"b": "c"}], "d": null}"#).unwrap();
+        let a = parsed.get("a").unwrap().as_array().unwrap();
+        assert_eq!(a[0], JsonValue::Number(1.0));
+        assert_eq!(a[2].get("b").unwrap().as_str().unwrap(), "c");
+        assert!(parsed.get("d").unwrap().is_null());
+    }
+
+    #[test]
+    fn rejects_trailing_garbage_and_unterminated_strings() {
+        assert!(JsonValue::parse("{} extra").is_err());
+        assert!(JsonValue::parse("\"unterminated").is_err());
+    }
+}
@@ -0,0 +1,272 @@
+/*
+ copyright: (c) 2013-2018 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! A Merkle Mountain Range (MMR) accumulator: an append-only structure that commits to every
+//! leaf ever added without having to keep the whole leaf set in memory, and that can produce
+//! inclusion proofs against any historical peak set. Generic over what's being accumulated --
+//! `chainstate::burn::operations::leader_key_register` keeps one MMR per fork segment over its
+//! per-block consensus hashes (for pruned-mode consensus-hash freshness checks) and a second over
+//! its registered VRF public keys, sorted by key bytes (for pruned-mode key-uniqueness checks via
+//! `VRFKeyUniquenessProof`), so neither check requires a full re-scan of that fork's history.
+
+use util::hash::DoubleSha256;
+
+/// One node's hash in the MMR.
+pub type MMRHash = DoubleSha256;
+
+fn hash_leaf(data: &[u8]) -> MMRHash {
+    let mut buf = vec![0x00]; // leaf domain tag
+    buf.extend_from_slice(data);
+    DoubleSha256::from_data(&buf)
+}
+
+fn hash_node(left: &MMRHash, right: &MMRHash) -> MMRHash {
+    let mut buf = vec![0x01]; // internal-node domain tag
+    buf.extend_from_slice(left.as_bytes());
+    buf.extend_from_slice(right.as_bytes());
+    DoubleSha256::from_data(&buf)
+}
+
+// number of trailing one-bits in `n`, i.e. the height of the mountain completed by appending
+// the `n`-th leaf (0-indexed count of appends so far).
+fn trailing_ones(mut n: u64) -> u32 {
+    let mut count = 0;
+    while n & 1 == 1 {
+        count += 1;
+        n >>= 1;
+    }
+    count
+}
+
+/// An append-only Merkle Mountain Range over arbitrary leaf byte strings (registered VRF public
+/// keys, per-block consensus hashes, ...). Each append is O(log n) amortized; peaks are bagged
+/// into a single root on demand.
+pub struct MerkleMountainRange {
+    /// hashes of each perfect binary "mountain" accumulated so far, ordered from tallest to
+    /// shortest
+    peaks: Vec<MMRHash>,
+    num_leaves: u64,
+}
+
+impl MerkleMountainRange {
+    pub fn new() -> MerkleMountainRange {
+        MerkleMountainRange {
+            peaks: vec![],
+            num_leaves: 0,
+        }
+    }
+
+    /// Appends a new leaf (e.g. the serialized bytes of a registered VRF public key) to the MMR.
+    pub fn append(&mut self, leaf_data: &[u8]) {
+        let mut carry = hash_leaf(leaf_data);
+        let merges = trailing_ones(self.num_leaves);
+
+        for _ in 0..merges {
+            let left = self.peaks.pop().expect("FATAL: MMR peak/leaf-count invariant violated");
+            carry = hash_node(&left, &carry);
+        }
+
+        self.peaks.push(carry);
+        self.num_leaves += 1;
+    }
+
+    pub fn num_leaves(&self) -> u64 {
+        self.num_leaves
+    }
+
+    /// Bags the current peaks into a single root commitment. Returns `None` if the MMR is
+    /// empty. Peaks are bagged right-to-left (shortest mountain first) per the usual MMR
+    /// "bagging the peaks" convention.
+    pub fn root(&self) -> Option<MMRHash> {
+        let mut iter = self.peaks.iter().rev();
+        let first = iter.next()?.clone();
+        Some(iter.fold(first, |acc, peak| hash_node(peak, &acc)))
+    }
+
+    /// The current peak set, tallest mountain first -- what a pruned node persists instead of
+    /// the full leaf history, and what `verify_proof` checks a proof against.
+    pub fn peaks(&self) -> &[MMRHash] {
+        &self.peaks
+    }
+}
+
+/// An inclusion proof that a leaf is present under one of an MMR's peaks: which peak it lives
+/// under, its position within that peak's subtree, and the sibling hash at each level of its
+/// path up to the peak.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MMRInclusionProof {
+    pub peak_index: usize,
+    pub leaf_index: u64,
+    pub siblings: Vec<MMRHash>,
+}
+
+/// Verifies that `leaf_data` is included under one of `peaks` via `proof`: walks
+/// `proof.siblings` from the leaf upward, combining on whichever side the current index's low
+/// bit indicates, the same walk `accumulator::verify_and_prune` uses for its own forest -- then
+/// checks the recombined hash matches the peak `proof.peak_index` claims to live under.
+pub fn verify_proof(leaf_data: &[u8], proof: &MMRInclusionProof, peaks: &[MMRHash]) -> bool {
+    let claimed_peak = match peaks.get(proof.peak_index) {
+        Some(peak) => peak,
+        None => return false,
+    };
+
+    let mut cur = hash_leaf(leaf_data);
+    let mut index = proof.leaf_index;
+
+    for sibling in proof.siblings.iter() {
+        cur = if index & 1 == 0 { hash_node(&cur, sibling) } else { hash_node(sibling, &cur) };
+        index >>= 1;
+    }
+
+    &cur == claimed_peak
+}
+
+/// Rebuilds the MMR from every leaf ever appended, in append order, and produces an inclusion
+/// proof for the leaf at `target_index` -- the compatibility path a node that still keeps the
+/// full leaf history uses to hand a pruned node a proof it can check against just its peak set,
+/// without either side needing to agree on when pruning last happened. Mirrors
+/// `accumulator::build_proof_from_table`. Returns `None` if `target_index` is out of bounds.
+pub fn build_proof(leaves: &[Vec<u8>], target_index: usize) -> Option<MMRInclusionProof> {
+    if target_index >= leaves.len() {
+        return None;
+    }
+    let target_leaf = hash_leaf(&leaves[target_index]);
+
+    // Unlike `append`, which only needs each peak's combined hash, this replay also needs each
+    // peak's underlying leaves in order, so a proof can be read back off of whichever peak ends
+    // up holding `target_index`.
+    let mut peaks: Vec<Vec<MMRHash>> = Vec::new();
+    let mut num_leaves: u64 = 0;
+
+    for leaf_data in leaves.iter() {
+        let mut level = vec![hash_leaf(leaf_data)];
+        let merges = trailing_ones(num_leaves);
+
+        for _ in 0..merges {
+            let mut left = peaks.pop().expect("FATAL: MMR peak/leaf-count invariant violated");
+            left.extend(level);
+            level = left;
+        }
+
+        peaks.push(level);
+        num_leaves += 1;
+    }
+
+    for (peak_index, level_leaves) in peaks.iter().enumerate() {
+        let leaf_index = match level_leaves.iter().position(|h| *h == target_leaf) {
+            Some(i) => i,
+            None => continue,
+        };
+
+        let mut level = level_leaves.clone();
+        let mut position = leaf_index;
+        let mut siblings = Vec::new();
+
+        while level.len() > 1 {
+            siblings.push(level[position ^ 1].clone());
+            position /= 2;
+            level = level.chunks(2).map(|pair| hash_node(&pair[0], &pair[1])).collect();
+        }
+
+        return Some(MMRInclusionProof {
+            peak_index,
+            leaf_index: leaf_index as u64,
+            siblings,
+        });
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_mmr_has_no_root() {
+        let mmr = MerkleMountainRange::new();
+        assert_eq!(mmr.root(), None);
+        assert_eq!(mmr.num_leaves(), 0);
+    }
+
+    #[test]
+    fn root_changes_on_append_and_is_deterministic() {
+        let mut mmr_a = MerkleMountainRange::new();
+        let mut mmr_b = MerkleMountainRange::new();
+
+        mmr_a.append(b"vrf-pubkey-1");
+        let root_after_one = mmr_a.root().unwrap();
+
+        mmr_b.append(b"vrf-pubkey-1");
+        assert_eq!(root_after_one, mmr_b.root().unwrap());
+
+        mmr_a.append(b"vrf-pubkey-2");
+        assert_ne!(root_after_one, mmr_a.root().unwrap());
+        assert_eq!(mmr_a.num_leaves(), 2);
+    }
+
+    #[test]
+    fn distinct_leaf_sets_yield_distinct_roots() {
+        let mut mmr_a = MerkleMountainRange::new();
+        let mut mmr_b = MerkleMountainRange::new();
+
+        for i in 0..7u8 {
+            mmr_a.append(&[i]);
+        }
+        for i in 0..7u8 {
+            mmr_b.append(&[i + 1]);
+        }
+
+        assert_ne!(mmr_a.root(), mmr_b.root());
+    }
+
+    #[test]
+    fn builds_and_verifies_a_proof_at_every_position_of_a_non_power_of_two_mmr() {
+        let leaves: Vec<Vec<u8>> = (0..7u8).map(|i| vec![i]).collect();
+
+        let mut mmr = MerkleMountainRange::new();
+        for leaf in leaves.iter() {
+            mmr.append(leaf);
+        }
+
+        for target_index in 0..leaves.len() {
+            let proof = build_proof(&leaves, target_index).unwrap();
+            assert!(verify_proof(&leaves[target_index], &proof, mmr.peaks()));
+        }
+    }
+
+    #[test]
+    fn verify_proof_rejects_a_proof_for_the_wrong_leaf() {
+        let leaves: Vec<Vec<u8>> = (0..4u8).map(|i| vec![i]).collect();
+
+        let mut mmr = MerkleMountainRange::new();
+        for leaf in leaves.iter() {
+            mmr.append(leaf);
+        }
+
+        let proof = build_proof(&leaves, 1).unwrap();
+        assert!(!verify_proof(&[99u8], &proof, mmr.peaks()));
+    }
+
+    #[test]
+    fn build_proof_is_none_for_an_out_of_bounds_index() {
+        let leaves: Vec<Vec<u8>> = (0..3u8).map(|i| vec![i]).collect();
+        assert_eq!(build_proof(&leaves, 3), None);
+    }
+}
@@ -0,0 +1,251 @@
+/*
+ copyright: (c) 2013-2018 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! The Clarity type checker: walks a contract's expressions, resolving and admitting types.
+
+pub mod natives;
+
+use std::collections::{HashMap, HashSet};
+
+use vm::representations::SymbolicExpression;
+use vm::types::{AtomTypeIdentifier, TypeSignature};
+use vm::checker::typecheck::natives::maps::{MapReference, MapAccess};
+
+#[cfg(feature = "developer-mode")]
+use vm::representations::Span;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CheckErrors {
+    IncorrectArgumentCount(usize, usize),
+    ContractCallExpectName,
+    BadMapName,
+    NoSuchMap(String),
+    TypeError(TypeSignature, TypeSignature),
+    /// The accumulated cost bound for a function exceeded its configured block limit (or
+    /// overflowed `u64` outright) -- see `TypeChecker::accumulate_cost`.
+    CostOverflow,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckError {
+    pub err: CheckErrors,
+    /// The span of the expression that triggered `err`, if any. Only present in
+    /// `developer-mode` builds -- see `vm::representations`.
+    #[cfg(feature = "developer-mode")]
+    pub diagnostic: Option<Span>,
+}
+
+impl CheckError {
+    pub fn new(err: CheckErrors) -> CheckError {
+        CheckError {
+            err,
+            #[cfg(feature = "developer-mode")]
+            diagnostic: None,
+        }
+    }
+}
+
+pub type TypeResult = Result<TypeSignature, CheckError>;
+
+/// Ambient context threaded through a single `type_check` call -- reserved for scoping of
+/// locally-bound names (`let`, function arguments) once a full expression dispatcher exists.
+/// The map checkers in `natives::maps` accept it but don't yet need anything from it.
+pub struct TypingContext;
+
+pub fn no_type() -> TypeSignature {
+    TypeSignature::new_atom(AtomTypeIdentifier::VoidType)
+}
+
+/// The maps a single contract defines, by name.
+pub struct ContractContext {
+    maps: HashMap<String, (TypeSignature, TypeSignature)>,
+}
+
+impl ContractContext {
+    pub fn new() -> ContractContext {
+        ContractContext { maps: HashMap::new() }
+    }
+
+    pub fn define_map(&mut self, map_name: String, key_type: TypeSignature, value_type: TypeSignature) {
+        self.maps.insert(map_name, (key_type, value_type));
+    }
+
+    pub fn get_map_type(&self, map_name: &str) -> Option<(TypeSignature, TypeSignature)> {
+        self.maps.get(map_name).cloned()
+    }
+}
+
+/// Lookup of other contracts' map types, for `check_special_fetch_contract_entry`. Stands in
+/// for the on-disk contract-analysis database; a real node looks this up from already-analyzed,
+/// already-deployed contracts instead of holding them all in memory.
+pub struct AnalysisDatabase {
+    contracts: HashMap<String, ContractContext>,
+}
+
+impl AnalysisDatabase {
+    pub fn new() -> AnalysisDatabase {
+        AnalysisDatabase { contracts: HashMap::new() }
+    }
+
+    pub fn register_contract(&mut self, contract_name: String, context: ContractContext) {
+        self.contracts.insert(contract_name, context);
+    }
+
+    pub fn get_map_type(&self, contract_name: &str, map_name: &str) -> Result<(TypeSignature, TypeSignature), CheckError> {
+        self.contracts.get(contract_name)
+            .and_then(|context| context.get_map_type(map_name))
+            .ok_or_else(|| CheckError::new(CheckErrors::NoSuchMap(map_name.to_string())))
+    }
+}
+
+/// Per-function summary of which persisted maps a function reads from and/or writes to,
+/// populated by `TypeChecker::record_map_access` and surfaced via `ContractAnalysis`. Enables
+/// static detection of functions that write a map without declaring intent, and of functions
+/// whose read/write sets are disjoint and so can be flagged as safe to evaluate concurrently.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MapAccessSummary {
+    pub reads: HashSet<MapReference>,
+    pub writes: HashSet<MapReference>,
+}
+
+/// An upper bound on the serialized bytes a function's persisted-map operations could touch,
+/// accumulated by `TypeChecker::accumulate_cost` -- see `natives::maps::operation_cost`. This
+/// is a deterministic, pre-deployment gas ceiling: wallets and the miner can use it to reject
+/// or price a contract before ever executing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RuntimeCostBound(pub u64);
+
+/// The result of type-checking a contract: a read/write map-access summary and a cost bound,
+/// per top-level function. Produced by `TypeChecker::into_contract_analysis` once type checking
+/// completes.
+pub struct ContractAnalysis {
+    map_accesses_by_function: HashMap<String, MapAccessSummary>,
+    cost_bounds_by_function: HashMap<String, RuntimeCostBound>,
+}
+
+impl ContractAnalysis {
+    pub fn map_accesses_for_function(&self, function_name: &str) -> Option<&MapAccessSummary> {
+        self.map_accesses_by_function.get(function_name)
+    }
+
+    pub fn cost_bound_for_function(&self, function_name: &str) -> Option<RuntimeCostBound> {
+        self.cost_bounds_by_function.get(function_name).cloned()
+    }
+}
+
+/// Walks a contract's expressions, resolving and admitting types. Also accumulates the
+/// per-function map-access summary and cost bound surfaced in `ContractAnalysis`.
+pub struct TypeChecker {
+    pub type_map: TypeMap,
+    pub contract_context: ContractContext,
+    pub db: AnalysisDatabase,
+
+    current_function: Option<String>,
+    map_accesses: HashMap<String, MapAccessSummary>,
+    cost_bounds: HashMap<String, RuntimeCostBound>,
+    block_limit: RuntimeCostBound,
+}
+
+/// Stand-in for a table of the resolved types of already-visited expressions. It stores
+/// nothing: `set_type` accepts a type and discards it.
+pub struct TypeMap;
+
+impl TypeMap {
+    pub fn new() -> TypeMap {
+        TypeMap
+    }
+
+    pub fn set_type(&mut self, _expr: &SymbolicExpression, _type_sig: TypeSignature) -> Result<(), CheckError> {
+        Ok(())
+    }
+}
+
+impl TypeChecker {
+    pub fn new(contract_context: ContractContext, db: AnalysisDatabase, block_limit: RuntimeCostBound) -> TypeChecker {
+        TypeChecker {
+            type_map: TypeMap::new(),
+            contract_context,
+            db,
+            current_function: None,
+            map_accesses: HashMap::new(),
+            cost_bounds: HashMap::new(),
+            block_limit,
+        }
+    }
+
+    /// Scopes subsequent `record_map_access` calls to `function_name`, until the next call to
+    /// `enter_function`.
+    pub fn enter_function(&mut self, function_name: &str) {
+        self.current_function = Some(function_name.to_string());
+    }
+
+    /// Resolves the type of `expr`. A stand-in for the full recursive-descent dispatcher
+    /// (`if`, `let`, user-defined calls, ...) that doesn't exist in this tree yet; the map
+    /// checkers in `natives::maps` only need it to resolve the type of a key or value argument.
+    pub fn type_check(&mut self, expr: &SymbolicExpression, _context: &TypingContext) -> TypeResult {
+        match expr.match_atom() {
+            Some(name) => {
+                if let Some(max_len) = name.strip_prefix("buff:").and_then(|rest| rest.parse::<u32>().ok()) {
+                    Ok(TypeSignature::new_atom(AtomTypeIdentifier::BufferType(max_len)))
+                } else if name == "true" || name == "false" {
+                    Ok(TypeSignature::new_atom(AtomTypeIdentifier::BoolType))
+                } else {
+                    Ok(TypeSignature::new_atom(AtomTypeIdentifier::IntType))
+                }
+            },
+            None => Ok(no_type()),
+        }
+    }
+
+    /// Folds `map_ref` into the currently-entered function's read or write set.
+    pub fn record_map_access(&mut self, map_ref: MapReference, access: MapAccess) {
+        let function_name = self.current_function.clone().unwrap_or_default();
+        let summary = self.map_accesses.entry(function_name).or_insert_with(MapAccessSummary::default);
+        match access {
+            MapAccess::Read => { summary.reads.insert(map_ref); },
+            MapAccess::Write => { summary.writes.insert(map_ref); },
+        }
+    }
+
+    /// Adds `cost` to the currently-entered function's running cost bound, failing with
+    /// `CheckErrors::CostOverflow` if that would overflow `u64` or exceed `block_limit`.
+    pub fn accumulate_cost(&mut self, cost: u64) -> Result<(), CheckError> {
+        let function_name = self.current_function.clone().unwrap_or_default();
+        let current = self.cost_bounds.get(&function_name).cloned().unwrap_or(RuntimeCostBound(0));
+
+        let updated = current.0.checked_add(cost)
+            .ok_or_else(|| CheckError::new(CheckErrors::CostOverflow))?;
+
+        if updated > self.block_limit.0 {
+            return Err(CheckError::new(CheckErrors::CostOverflow));
+        }
+
+        self.cost_bounds.insert(function_name, RuntimeCostBound(updated));
+        Ok(())
+    }
+
+    /// Finalizes this checker's bookkeeping into the `ContractAnalysis` returned once a
+    /// contract finishes type-checking.
+    pub fn into_contract_analysis(self) -> ContractAnalysis {
+        ContractAnalysis {
+            map_accesses_by_function: self.map_accesses,
+            cost_bounds_by_function: self.cost_bounds,
+        }
+    }
+}
@@ -1,26 +1,72 @@
 use vm::representations::{SymbolicExpression};
 use vm::types::{AtomTypeIdentifier, TypeSignature};
 
-use vm::checker::typecheck::{TypeResult, TypingContext, 
+use vm::checker::typecheck::{TypeResult, TypingContext,
                              CheckError, CheckErrors, no_type, TypeChecker};
 
+/// Identifies a persisted map that a function reads from or writes to, for the purposes of
+/// the checker's per-function access-summary analysis (see `TypeChecker::record_map_access`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MapReference {
+    Local { map_name: String },
+    Contract { contract_name: String, map_name: String },
+}
+
+/// Whether a map checker in this module is resolving a read or a write, so that the caller
+/// can fold the reference into the right half of the currently-checked function's access set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapAccess {
+    Read,
+    Write,
+}
+
+// Upper bound, in bytes, on the serialized size of the key plus (if given) the value touched
+// by one occurrence of a map operation. Charged once per syntactic occurrence in the AST, since
+// recursion/loop bounds aren't known to the checker -- see `TypeChecker::accumulate_cost`.
+fn operation_cost(key_type: &TypeSignature, value_type: Option<&TypeSignature>) -> u64 {
+    let mut cost = key_type.max_serialized_size();
+    if let Some(value_type) = value_type {
+        cost += value_type.max_serialized_size();
+    }
+    cost
+}
+
+// Builds a `CheckError` that carries the span of the offending `SymbolicExpression`, so that
+// editors/linters/formatters built on top of the checker can map a failure back to source.
+// `CheckError::diagnostic` and `SymbolicExpression::span` only exist under `developer-mode`, so
+// on a default build this is a no-op wrapper around `CheckError::new`.
+#[cfg(feature = "developer-mode")]
+fn diagnostic_err(err: CheckErrors, expr: &SymbolicExpression) -> CheckError {
+    let mut check_err = CheckError::new(err);
+    check_err.diagnostic = expr.span.clone();
+    check_err
+}
+
+#[cfg(not(feature = "developer-mode"))]
+fn diagnostic_err(err: CheckErrors, _expr: &SymbolicExpression) -> CheckError {
+    CheckError::new(err)
+}
+
 pub fn check_special_fetch_entry(checker: &mut TypeChecker, args: &[SymbolicExpression], context: &TypingContext) -> TypeResult {
     if args.len() < 2 {
         return Err(CheckError::new(CheckErrors::IncorrectArgumentCount(2, args.len())))
     }
 
     let map_name = args[0].match_atom()
-        .ok_or(CheckError::new(CheckErrors::BadMapName))?;
-        
+        .ok_or(diagnostic_err(CheckErrors::BadMapName, &args[0]))?;
+
     checker.type_map.set_type(&args[0], no_type())?;
 
     let key_type = checker.type_check(&args[1], context)?;
 
     let (expected_key_type, value_type) = checker.contract_context.get_map_type(map_name)
-        .ok_or(CheckError::new(CheckErrors::NoSuchMap(map_name.clone())))?;
+        .ok_or(diagnostic_err(CheckErrors::NoSuchMap(map_name.to_string()), &args[0]))?;
+
+    checker.record_map_access(MapReference::Local { map_name: map_name.to_string() }, MapAccess::Read);
+    checker.accumulate_cost(operation_cost(&expected_key_type, Some(&value_type)))?;
 
     if !expected_key_type.admits_type(&key_type) {
-        return Err(CheckError::new(CheckErrors::TypeError(expected_key_type.clone(), key_type)))
+        return Err(diagnostic_err(CheckErrors::TypeError(expected_key_type.clone(), key_type), &args[1]))
     } else {
         return Ok(value_type.clone())
     }
@@ -32,20 +78,26 @@ pub fn check_special_fetch_contract_entry(checker: &mut TypeChecker, args: &[Sym
     }
     
     let contract_name = args[0].match_atom()
-        .ok_or(CheckError::new(CheckErrors::ContractCallExpectName))?;
-    
+        .ok_or(diagnostic_err(CheckErrors::ContractCallExpectName, &args[0]))?;
+
     let map_name = args[1].match_atom()
-        .ok_or(CheckError::new(CheckErrors::BadMapName))?;
-    
+        .ok_or(diagnostic_err(CheckErrors::BadMapName, &args[1]))?;
+
     checker.type_map.set_type(&args[0], no_type())?;
     checker.type_map.set_type(&args[1], no_type())?;
-    
+
     let key_type = checker.type_check(&args[2], context)?;
-    
+
     let (expected_key_type, value_type) = checker.db.get_map_type(contract_name, map_name)?;
-    
+
+    checker.record_map_access(MapReference::Contract {
+        contract_name: contract_name.to_string(),
+        map_name: map_name.to_string()
+    }, MapAccess::Read);
+    checker.accumulate_cost(operation_cost(&expected_key_type, Some(&value_type)))?;
+
     if !expected_key_type.admits_type(&key_type) {
-        return Err(CheckError::new(CheckErrors::TypeError(expected_key_type.clone(), key_type)))
+        return Err(diagnostic_err(CheckErrors::TypeError(expected_key_type.clone(), key_type), &args[2]))
     } else {
         return Ok(value_type)
     }
@@ -57,17 +109,20 @@ pub fn check_special_delete_entry(checker: &mut TypeChecker, args: &[SymbolicExp
     }
 
     let map_name = args[0].match_atom()
-        .ok_or(CheckError::new(CheckErrors::BadMapName))?;
+        .ok_or(diagnostic_err(CheckErrors::BadMapName, &args[0]))?;
 
     checker.type_map.set_type(&args[0], no_type())?;
 
     let key_type = checker.type_check(&args[1], context)?;
-    
+
     let (expected_key_type, _) = checker.contract_context.get_map_type(map_name)
-        .ok_or(CheckError::new(CheckErrors::NoSuchMap(map_name.clone())))?;
-    
+        .ok_or(diagnostic_err(CheckErrors::NoSuchMap(map_name.to_string()), &args[0]))?;
+
+    checker.record_map_access(MapReference::Local { map_name: map_name.to_string() }, MapAccess::Write);
+    checker.accumulate_cost(operation_cost(&expected_key_type, None))?;
+
     if !expected_key_type.admits_type(&key_type) {
-        return Err(CheckError::new(CheckErrors::TypeError(expected_key_type.clone(), key_type)))
+        return Err(diagnostic_err(CheckErrors::TypeError(expected_key_type.clone(), key_type), &args[1]))
     } else {
         return Ok(TypeSignature::new_atom(AtomTypeIdentifier::BoolType))
     }
@@ -79,20 +134,23 @@ pub fn check_special_set_entry(checker: &mut TypeChecker, args: &[SymbolicExpres
     }
     
     let map_name = args[0].match_atom()
-        .ok_or(CheckError::new(CheckErrors::BadMapName))?;
-    
+        .ok_or(diagnostic_err(CheckErrors::BadMapName, &args[0]))?;
+
     checker.type_map.set_type(&args[0], no_type())?;
-    
+
     let key_type = checker.type_check(&args[1], context)?;
     let value_type = checker.type_check(&args[2], context)?;
-    
+
     let (expected_key_type, expected_value_type) = checker.contract_context.get_map_type(map_name)
-        .ok_or(CheckError::new(CheckErrors::NoSuchMap(map_name.clone())))?;
-    
+        .ok_or(diagnostic_err(CheckErrors::NoSuchMap(map_name.to_string()), &args[0]))?;
+
+    checker.record_map_access(MapReference::Local { map_name: map_name.to_string() }, MapAccess::Write);
+    checker.accumulate_cost(operation_cost(&expected_key_type, Some(&expected_value_type)))?;
+
     if !expected_key_type.admits_type(&key_type) {
-        return Err(CheckError::new(CheckErrors::TypeError(expected_key_type.clone(), key_type)))
+        return Err(diagnostic_err(CheckErrors::TypeError(expected_key_type.clone(), key_type), &args[1]))
     } else if !expected_value_type.admits_type(&value_type) {
-        return Err(CheckError::new(CheckErrors::TypeError(expected_key_type.clone(), key_type)))
+        return Err(diagnostic_err(CheckErrors::TypeError(expected_value_type.clone(), value_type), &args[2]))
     } else {
         return Ok(TypeSignature::new_atom(AtomTypeIdentifier::VoidType))
     }
@@ -102,23 +160,150 @@ pub fn check_special_insert_entry(checker: &mut TypeChecker, args: &[SymbolicExp
     if args.len() < 3 {
         return Err(CheckError::new(CheckErrors::IncorrectArgumentCount(3, args.len())))
     }
-    
+
     let map_name = args[0].match_atom()
-        .ok_or(CheckError::new(CheckErrors::BadMapName))?;
-    
+        .ok_or(diagnostic_err(CheckErrors::BadMapName, &args[0]))?;
+
     checker.type_map.set_type(&args[0], no_type())?;
-    
+
     let key_type = checker.type_check(&args[1], context)?;
     let value_type = checker.type_check(&args[2], context)?;
-    
+
     let (expected_key_type, expected_value_type) = checker.contract_context.get_map_type(map_name)
-        .ok_or(CheckError::new(CheckErrors::NoSuchMap(map_name.clone())))?;
-    
+        .ok_or(diagnostic_err(CheckErrors::NoSuchMap(map_name.to_string()), &args[0]))?;
+
+    checker.record_map_access(MapReference::Local { map_name: map_name.to_string() }, MapAccess::Write);
+    checker.accumulate_cost(operation_cost(&expected_key_type, Some(&expected_value_type)))?;
+
     if !expected_key_type.admits_type(&key_type) {
-        return Err(CheckError::new(CheckErrors::TypeError(expected_key_type.clone(), key_type)))
+        return Err(diagnostic_err(CheckErrors::TypeError(expected_key_type.clone(), key_type), &args[1]))
     } else if !expected_value_type.admits_type(&value_type) {
-        return Err(CheckError::new(CheckErrors::TypeError(expected_key_type.clone(), key_type)))
+        return Err(diagnostic_err(CheckErrors::TypeError(expected_value_type.clone(), value_type), &args[2]))
     } else {
         return Ok(TypeSignature::new_atom(AtomTypeIdentifier::BoolType))
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vm::checker::typecheck::{ContractContext, AnalysisDatabase, TypeChecker, RuntimeCostBound};
+
+    fn int_type() -> TypeSignature {
+        TypeSignature::new_atom(AtomTypeIdentifier::IntType)
+    }
+
+    fn bool_type() -> TypeSignature {
+        TypeSignature::new_atom(AtomTypeIdentifier::BoolType)
+    }
+
+    fn new_checker_with_map(map_name: &str, key_type: TypeSignature, value_type: TypeSignature) -> TypeChecker {
+        let mut contract_context = ContractContext::new();
+        contract_context.define_map(map_name.to_string(), key_type, value_type);
+        TypeChecker::new(contract_context, AnalysisDatabase::new(), RuntimeCostBound(u64::max_value()))
+    }
+
+    #[test]
+    fn map_reference_distinguishes_local_and_contract_maps() {
+        let local = MapReference::Local { map_name: "balances".to_string() };
+        let remote = MapReference::Contract { contract_name: "token".to_string(), map_name: "balances".to_string() };
+        assert_ne!(local, remote);
+    }
+
+    #[test]
+    fn map_reference_equality_is_by_name() {
+        let a = MapReference::Local { map_name: "balances".to_string() };
+        let b = MapReference::Local { map_name: "balances".to_string() };
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn operation_cost_charges_key_and_value_for_reads_and_writes() {
+        let key_only = operation_cost(&TypeSignature::new_atom(AtomTypeIdentifier::BoolType), None);
+        let key_and_value = operation_cost(
+            &TypeSignature::new_atom(AtomTypeIdentifier::BoolType),
+            Some(&TypeSignature::new_atom(AtomTypeIdentifier::BoolType)));
+
+        // a delete-entry-style charge (key only) must be strictly cheaper than a
+        // set-entry-style charge (key + value) against the same types.
+        assert!(key_only < key_and_value);
+    }
+
+    #[test]
+    fn record_map_access_tracks_local_map_reads() {
+        let mut checker = new_checker_with_map("balances", int_type(), bool_type());
+        checker.enter_function("get-balance");
+
+        let args = [SymbolicExpression::atom("balances".to_string()), SymbolicExpression::atom("1".to_string())];
+        check_special_fetch_entry(&mut checker, &args, &TypingContext).unwrap();
+
+        let analysis = checker.into_contract_analysis();
+        let summary = analysis.map_accesses_for_function("get-balance").unwrap();
+        assert_eq!(summary.reads, [MapReference::Local { map_name: "balances".to_string() }].iter().cloned().collect());
+        assert!(summary.writes.is_empty());
+    }
+
+    #[test]
+    fn record_map_access_tracks_cross_contract_reads() {
+        let mut checker = new_checker_with_map("unused", int_type(), bool_type());
+        let mut token_contract = ContractContext::new();
+        token_contract.define_map("balances".to_string(), int_type(), bool_type());
+        checker.db.register_contract("token".to_string(), token_contract);
+        checker.enter_function("get-token-balance");
+
+        let args = [
+            SymbolicExpression::atom("token".to_string()),
+            SymbolicExpression::atom("balances".to_string()),
+            SymbolicExpression::atom("1".to_string()),
+        ];
+        check_special_fetch_contract_entry(&mut checker, &args, &TypingContext).unwrap();
+
+        let analysis = checker.into_contract_analysis();
+        let summary = analysis.map_accesses_for_function("get-token-balance").unwrap();
+        assert_eq!(summary.reads, [MapReference::Contract { contract_name: "token".to_string(), map_name: "balances".to_string() }].iter().cloned().collect());
+        assert!(summary.writes.is_empty());
+    }
+
+    #[test]
+    fn record_map_access_tracks_both_read_and_write_in_one_function() {
+        let mut checker = new_checker_with_map("balances", int_type(), bool_type());
+        checker.enter_function("transfer");
+
+        let fetch_args = [SymbolicExpression::atom("balances".to_string()), SymbolicExpression::atom("1".to_string())];
+        check_special_fetch_entry(&mut checker, &fetch_args, &TypingContext).unwrap();
+
+        let set_args = [
+            SymbolicExpression::atom("balances".to_string()),
+            SymbolicExpression::atom("1".to_string()),
+            SymbolicExpression::atom("true".to_string()),
+        ];
+        check_special_set_entry(&mut checker, &set_args, &TypingContext).unwrap();
+
+        let analysis = checker.into_contract_analysis();
+        let summary = analysis.map_accesses_for_function("transfer").unwrap();
+        let expected = MapReference::Local { map_name: "balances".to_string() };
+        assert!(summary.reads.contains(&expected));
+        assert!(summary.writes.contains(&expected));
+    }
+
+    #[test]
+    fn large_buffer_valued_map_yields_a_proportionally_larger_cost_bound_than_a_bool_valued_map() {
+        let bool_cost = operation_cost(&int_type(), Some(&bool_type()));
+        let buffer_cost = operation_cost(&int_type(), Some(&TypeSignature::new_atom(AtomTypeIdentifier::BufferType(1024))));
+
+        assert!(buffer_cost > bool_cost * 10);
+    }
+
+    #[test]
+    fn accumulate_cost_fails_once_the_block_limit_is_exceeded() {
+        let mut contract_context = ContractContext::new();
+        contract_context.define_map("balances".to_string(), int_type(), bool_type());
+        let mut checker = TypeChecker::new(contract_context, AnalysisDatabase::new(), RuntimeCostBound(1));
+        checker.enter_function("get-balance");
+
+        let args = [SymbolicExpression::atom("balances".to_string()), SymbolicExpression::atom("1".to_string())];
+        let result = check_special_fetch_entry(&mut checker, &args, &TypingContext);
+
+        assert_eq!(result.unwrap_err().err, CheckErrors::CostOverflow);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,3 @@
+pub mod representations;
+pub mod types;
+pub mod checker;
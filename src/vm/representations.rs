@@ -0,0 +1,122 @@
+/*
+ copyright: (c) 2013-2018 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Source-level representation of a parsed Clarity expression. `SymbolicExpression` is the AST
+//! node the checker walks; everything tooling-facing (source spans, retained comments) lives
+//! behind the `developer-mode` feature (see Cargo.toml) so a release build's `SymbolicExpression`
+//! is exactly as small, `Clone`-cheap, and serialization-stable as it was before that tooling
+//! existed.
+
+/// A half-open source location, in line/column form, that a parsed expression was read from.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start_line: u32,
+    pub start_column: u32,
+    pub end_line: u32,
+    pub end_column: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SymbolicExpressionType {
+    AtomValue(String),
+    Atom(String),
+    List(Box<[SymbolicExpression]>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolicExpression {
+    pub expr: SymbolicExpressionType,
+
+    /// Where `expr` was parsed from. Only present in `developer-mode` builds -- see the module
+    /// doc comment.
+    #[cfg(feature = "developer-mode")]
+    pub span: Option<Span>,
+    /// Comments the parser saw immediately before this expression, in source order. Only
+    /// present in `developer-mode` builds.
+    #[cfg(feature = "developer-mode")]
+    pub pre_comments: Vec<String>,
+    /// Comments the parser saw trailing this expression on the same line. Only present in
+    /// `developer-mode` builds.
+    #[cfg(feature = "developer-mode")]
+    pub post_comments: Vec<String>,
+}
+
+impl SymbolicExpression {
+    pub fn atom(name: String) -> SymbolicExpression {
+        SymbolicExpression {
+            expr: SymbolicExpressionType::Atom(name),
+            #[cfg(feature = "developer-mode")]
+            span: None,
+            #[cfg(feature = "developer-mode")]
+            pre_comments: vec![],
+            #[cfg(feature = "developer-mode")]
+            post_comments: vec![],
+        }
+    }
+
+    pub fn atom_value(value: String) -> SymbolicExpression {
+        SymbolicExpression {
+            expr: SymbolicExpressionType::AtomValue(value),
+            #[cfg(feature = "developer-mode")]
+            span: None,
+            #[cfg(feature = "developer-mode")]
+            pre_comments: vec![],
+            #[cfg(feature = "developer-mode")]
+            post_comments: vec![],
+        }
+    }
+
+    pub fn list(children: Box<[SymbolicExpression]>) -> SymbolicExpression {
+        SymbolicExpression {
+            expr: SymbolicExpressionType::List(children),
+            #[cfg(feature = "developer-mode")]
+            span: None,
+            #[cfg(feature = "developer-mode")]
+            pre_comments: vec![],
+            #[cfg(feature = "developer-mode")]
+            post_comments: vec![],
+        }
+    }
+
+    /// If this expression is a bare atom (a map name, a variable reference, ...), its name.
+    pub fn match_atom(&self) -> Option<&str> {
+        match &self.expr {
+            SymbolicExpressionType::Atom(name) => Some(name.as_str()),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "developer-mode")]
+    pub fn with_span(mut self, span: Span) -> SymbolicExpression {
+        self.span = Some(span);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn match_atom_only_matches_atoms() {
+        assert_eq!(SymbolicExpression::atom("balances".to_string()).match_atom(), Some("balances"));
+        assert_eq!(SymbolicExpression::atom_value("42".to_string()).match_atom(), None);
+        assert_eq!(SymbolicExpression::list(Box::new([])).match_atom(), None);
+    }
+}
@@ -0,0 +1,80 @@
+/*
+ copyright: (c) 2013-2018 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Clarity's type signatures, as seen by the checker. `TypeSignature` is deliberately thin --
+//! it only carries what the checker in `checker::typecheck` needs (admission checks and a
+//! serialized-size bound for cost estimation), not a full value representation.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AtomTypeIdentifier {
+    VoidType,
+    BoolType,
+    IntType,
+    /// A byte buffer with a declared maximum length, e.g. Clarity's `(buff 32)`.
+    BufferType(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeSignature(AtomTypeIdentifier);
+
+impl TypeSignature {
+    pub fn new_atom(atom_type: AtomTypeIdentifier) -> TypeSignature {
+        TypeSignature(atom_type)
+    }
+
+    /// Whether a value of type `other` can be used where `self` is expected. Clarity has no
+    /// implicit widening, so this is just equality -- callers pass the expected type as `self`.
+    pub fn admits_type(&self, other: &TypeSignature) -> bool {
+        self.0 == other.0
+    }
+
+    /// Upper bound, in bytes, on this type's serialized wire size. Fixed for atoms; for a
+    /// buffer this is `4 + n * elem_size` (a 4-byte length prefix plus up to `n` one-byte
+    /// elements), per its declared maximum length `n`. Used to charge map operations a cost
+    /// proportional to the data they could move -- see `operation_cost` in
+    /// `checker::typecheck::natives::maps`.
+    pub fn max_serialized_size(&self) -> u64 {
+        match &self.0 {
+            AtomTypeIdentifier::VoidType => 1,
+            AtomTypeIdentifier::BoolType => 1,
+            AtomTypeIdentifier::IntType => 16,
+            AtomTypeIdentifier::BufferType(max_len) => 4 + (*max_len as u64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn admits_type_requires_exact_match() {
+        let bool_type = TypeSignature::new_atom(AtomTypeIdentifier::BoolType);
+        let int_type = TypeSignature::new_atom(AtomTypeIdentifier::IntType);
+        assert!(bool_type.admits_type(&bool_type));
+        assert!(!bool_type.admits_type(&int_type));
+    }
+
+    #[test]
+    fn larger_buffers_have_larger_size_bounds() {
+        let small = TypeSignature::new_atom(AtomTypeIdentifier::BufferType(8));
+        let large = TypeSignature::new_atom(AtomTypeIdentifier::BufferType(256));
+        assert!(large.max_serialized_size() > small.max_serialized_size());
+    }
+}